@@ -0,0 +1,147 @@
+use std::cmp::Ordering;
+
+// What moving a crab some distance costs. Any cost has to be convex in the
+// distance, so the total over all the crabs is convex in where they meet.
+pub enum Cost {
+    Linear,
+    // 1 + 2 + ... + distance
+    Triangular,
+    Quadratic,
+    Custom(Box<dyn Fn(u64) -> u64 + Send + Sync>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Alignment {
+    pub position: u64,
+    pub fuel: u64,
+}
+
+impl Cost {
+    pub fn custom(cost: impl Fn(u64) -> u64 + Send + Sync + 'static) -> Self {
+        Cost::Custom(Box::new(cost))
+    }
+
+    pub fn fuel(&self, distance: u64) -> u64 {
+        match self {
+            Cost::Linear => distance,
+            Cost::Triangular => distance * (distance + 1) / 2,
+            Cost::Quadratic => distance * distance,
+            Cost::Custom(cost) => cost(distance),
+        }
+    }
+}
+
+pub fn total_fuel(positions: &[u64], cost: &Cost, to: u64) -> u64 {
+    positions.iter().map(|&p| cost.fuel(p.abs_diff(to))).sum()
+}
+
+// The leftmost of the candidates that costs the least
+fn best_of(positions: &[u64], cost: &Cost, candidates: impl Iterator<Item = u64>) -> Alignment {
+    candidates
+        .map(|position| Alignment {
+            position,
+            fuel: total_fuel(positions, cost, position),
+        })
+        .min_by_key(|a| (a.fuel, a.position))
+        .unwrap()
+}
+
+// Where the crabs should meet to spend the least fuel, leftmost if there's
+// a choice, None without crabs
+pub fn align(positions: &[u64], cost: &Cost) -> Option<Alignment> {
+    let (&min, &max) = (positions.iter().min()?, positions.iter().max()?);
+    let n = positions.len() as u64;
+    let mean = positions.iter().sum::<u64>() / n;
+    let around = |from: u64, to: u64| from.max(min)..=to.min(max);
+    Some(match cost {
+        Cost::Linear => {
+            let mut sorted = positions.to_vec();
+            sorted.sort_unstable();
+            let median = sorted[(sorted.len() - 1) / 2];
+            Alignment {
+                position: median,
+                fuel: total_fuel(positions, cost, median),
+            }
+        }
+        // the slope is 0 within half a step of the mean
+        Cost::Triangular => best_of(positions, cost, around(mean.saturating_sub(1), mean + 2)),
+        Cost::Quadratic => best_of(positions, cost, around(mean, mean + 1)),
+        Cost::Custom(_) => {
+            let (mut low, mut high) = (min, max);
+            while high - low > 2 {
+                let third = (high - low) / 3;
+                let (left, right) = (low + third, high - third);
+                let (fuel_left, fuel_right) = (
+                    total_fuel(positions, cost, left),
+                    total_fuel(positions, cost, right),
+                );
+                // past the cheaper point it only gets dearer, and when they
+                // cost the same the leftmost best is somewhere before `right`
+                match fuel_left.cmp(&fuel_right) {
+                    Ordering::Less => high = right - 1,
+                    Ordering::Greater => low = left + 1,
+                    Ordering::Equal => high = right,
+                }
+            }
+            best_of(positions, cost, low..=high)
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn brute_force(positions: &[u64], cost: &Cost) -> Alignment {
+        let (min, max) = (
+            positions.iter().min().unwrap(),
+            positions.iter().max().unwrap(),
+        );
+        best_of(positions, cost, *min..=*max)
+    }
+
+    #[test]
+    fn example() {
+        let crabs = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+        assert_eq!(
+            align(&crabs, &Cost::Linear),
+            Some(Alignment {
+                position: 2,
+                fuel: 37
+            })
+        );
+        assert_eq!(
+            align(&crabs, &Cost::Triangular),
+            Some(Alignment {
+                position: 5,
+                fuel: 168
+            })
+        );
+        assert_eq!(align(&[], &Cost::Quadratic), None);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let costs = [
+            Cost::Linear,
+            Cost::Triangular,
+            Cost::Quadratic,
+            Cost::custom(|d| d * d * d),
+            // free up to 3 away, so plenty of ties
+            Cost::custom(|d| d.saturating_sub(3) * 10),
+        ];
+        let mut rng = Rng::new(0x2021_1207);
+        for size in 1..40 {
+            let crabs: Vec<_> = (0..size).map(|_| rng.below(100)).collect();
+            for cost in &costs {
+                assert_eq!(
+                    align(&crabs, cost),
+                    Some(brute_force(&crabs, cost)),
+                    "{:?}",
+                    crabs
+                );
+            }
+        }
+    }
+}
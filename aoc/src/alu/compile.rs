@@ -0,0 +1,134 @@
+use super::{Alu, Instruction, Operand, Runner};
+
+// Flat bytecode over a register array, one variant per opcode and operand
+// kind so running it is a single match with no operand lookups
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Inp(u8),
+    Clear(u8),
+    AddR(u8, u8),
+    AddI(u8, i64),
+    MulR(u8, u8),
+    MulI(u8, i64),
+    DivR(u8, u8),
+    DivI(u8, i64),
+    ModR(u8, u8),
+    ModI(u8, i64),
+    EqlR(u8, u8),
+    EqlI(u8, i64),
+    NeqlR(u8, u8),
+    NeqlI(u8, i64),
+    SetR(u8, u8),
+    SetI(u8, i64),
+}
+
+#[derive(Clone, Debug)]
+pub struct Compiled {
+    ops: Vec<Op>,
+}
+
+impl Compiled {
+    pub fn new(instructions: &[Instruction]) -> Self {
+        use Instruction::*;
+        // register and immediate flavours of an opcode
+        macro_rules! binary {
+            ($reg:expr, $op:expr, $r:ident, $i:ident) => {
+                match $op {
+                    Operand::Variable(src) => Op::$r($reg as u8, src as u8),
+                    Operand::Value(v) => Op::$i($reg as u8, v),
+                }
+            };
+        }
+        let ops = instructions
+            .iter()
+            .map(|&ins| match ins {
+                Inp(reg) => Op::Inp(reg as u8),
+                Clear(reg) => Op::Clear(reg as u8),
+                Add(reg, op) => binary!(reg, op, AddR, AddI),
+                Mul(reg, op) => binary!(reg, op, MulR, MulI),
+                Div(reg, op) => binary!(reg, op, DivR, DivI),
+                Mod(reg, op) => binary!(reg, op, ModR, ModI),
+                Eql(reg, op) => binary!(reg, op, EqlR, EqlI),
+                Neql(reg, op) => binary!(reg, op, NeqlR, NeqlI),
+                Set(reg, op) => binary!(reg, op, SetR, SetI),
+            })
+            .collect();
+        Compiled { ops }
+    }
+}
+
+impl Runner for Compiled {
+    fn run_for_input(&self, alu: &mut Alu, pc: usize, input: i64) -> usize {
+        // indexed like Register
+        let mut r = [alu.w, alu.x, alu.y, alu.z];
+        let mut input = Some(input);
+        let mut pc = pc;
+        // division by zero leaves the register alone, like the interpreter
+        let div = |l: i64, r: i64| l.checked_div(r).unwrap_or(l);
+        let rem = |l: i64, r: i64| l.checked_rem(r).unwrap_or(l);
+        while let Some(&op) = self.ops.get(pc) {
+            match op {
+                Op::Inp(a) => match input.take() {
+                    Some(v) => r[a as usize] = v,
+                    None => break,
+                },
+                Op::Clear(a) => r[a as usize] = 0,
+                Op::AddR(a, b) => r[a as usize] += r[b as usize],
+                Op::AddI(a, v) => r[a as usize] += v,
+                Op::MulR(a, b) => r[a as usize] *= r[b as usize],
+                Op::MulI(a, v) => r[a as usize] *= v,
+                Op::DivR(a, b) => r[a as usize] = div(r[a as usize], r[b as usize]),
+                Op::DivI(a, v) => r[a as usize] = div(r[a as usize], v),
+                Op::ModR(a, b) => r[a as usize] = rem(r[a as usize], r[b as usize]),
+                Op::ModI(a, v) => r[a as usize] = rem(r[a as usize], v),
+                Op::EqlR(a, b) => r[a as usize] = (r[a as usize] == r[b as usize]) as i64,
+                Op::EqlI(a, v) => r[a as usize] = (r[a as usize] == v) as i64,
+                Op::NeqlR(a, b) => r[a as usize] = (r[a as usize] != r[b as usize]) as i64,
+                Op::NeqlI(a, v) => r[a as usize] = (r[a as usize] != v) as i64,
+                Op::SetR(a, b) => r[a as usize] = r[b as usize],
+                Op::SetI(a, v) => r[a as usize] = v,
+            }
+            pc += 1;
+        }
+        [alu.w, alu.x, alu.y, alu.z] = r;
+        pc
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::alu::{parse_program, random_inputs};
+
+    // runs the whole program one input at a time
+    fn run_all<R: Runner + ?Sized>(program: &R, inputs: &[i64]) -> (Alu, usize) {
+        let mut alu = Alu::default();
+        let mut pc = 0;
+        for &input in inputs {
+            pc = program.run_for_input(&mut alu, pc, input);
+        }
+        (alu, pc)
+    }
+
+    #[test]
+    fn matches_interpreter() {
+        let source = concat!(
+            "inp w\nadd x w\nmul x 3\nset y x\nmod y 4\neql y 2\nneql y x\n",
+            "inp z\ndiv x z\nmod w y\nclear y\nadd y -7\nmul z y\ndiv w 0\n",
+            "inp y\neql z y\nneql w 1\nadd z x\nset w 5\n",
+        );
+        let program = parse_program(source.as_bytes()).unwrap();
+        let compiled = Compiled::new(&program);
+        for inputs in random_inputs(-3..=9, 3, 1).take(500) {
+            assert_eq!(
+                run_all(&compiled, &inputs),
+                run_all(&program[..], &inputs),
+                "{:?}",
+                inputs
+            );
+        }
+        // stops before an inp it has no input for
+        assert_eq!(run_all(&compiled, &[1]).1, 7);
+        assert_eq!(run_all(&program[..], &[1]).1, 7);
+    }
+}
@@ -0,0 +1,358 @@
+use std::ops::RangeInclusive;
+
+use anyhow::{Result, anyhow};
+use nom::{IResult, branch::alt, bytes::complete::tag, sequence::separated_pair};
+
+use crate::{
+    parse::{Diagnose, lines, parse_signed, whole_input},
+    rng::Rng,
+};
+
+pub mod compile;
+pub mod optimize;
+pub mod repl;
+pub mod symbolic;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Register {
+    W,
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operand {
+    Variable(Register),
+    Value(i64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    Inp(Register),
+    Add(Register, Operand),
+    Mul(Register, Operand),
+    Div(Register, Operand),
+    Mod(Register, Operand),
+    Eql(Register, Operand),
+    Clear(Register),
+    Set(Register, Operand),
+    Neql(Register, Operand),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Alu {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+    pub w: i64,
+}
+
+impl std::fmt::Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::X => write!(f, "x"),
+            Self::Y => write!(f, "y"),
+            Self::Z => write!(f, "z"),
+            Self::W => write!(f, "w"),
+        }
+    }
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Value(v) => write!(f, "{}", v),
+            Self::Variable(reg) => write!(f, "{}", reg),
+        }
+    }
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Inp(reg) => write!(f, "inp {}", reg),
+            Self::Add(reg, op) => write!(f, "add {} {}", reg, op),
+            Self::Mul(reg, op) => write!(f, "mul {} {}", reg, op),
+            Self::Div(reg, op) => write!(f, "div {} {}", reg, op),
+            Self::Mod(reg, op) => write!(f, "mod {} {}", reg, op),
+            Self::Eql(reg, op) => write!(f, "eql {} {}", reg, op),
+            Self::Clear(reg) => write!(f, "clear {}", reg),
+            Self::Set(reg, op) => write!(f, "set {} {}", reg, op),
+            Self::Neql(reg, op) => write!(f, "neql {} {}", reg, op),
+        }
+    }
+}
+
+impl std::fmt::Display for Alu {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "w={} x={} y={} z={}", self.w, self.x, self.y, self.z)
+    }
+}
+
+impl Instruction {
+    // Every instruction writes to its first register
+    pub fn target(&self) -> Register {
+        match *self {
+            Self::Inp(reg)
+            | Self::Add(reg, _)
+            | Self::Mul(reg, _)
+            | Self::Div(reg, _)
+            | Self::Mod(reg, _)
+            | Self::Eql(reg, _)
+            | Self::Clear(reg)
+            | Self::Set(reg, _)
+            | Self::Neql(reg, _) => reg,
+        }
+    }
+
+    fn operand(&self) -> Option<Operand> {
+        match *self {
+            Self::Inp(_) | Self::Clear(_) => None,
+            Self::Add(_, op)
+            | Self::Mul(_, op)
+            | Self::Div(_, op)
+            | Self::Mod(_, op)
+            | Self::Eql(_, op)
+            | Self::Set(_, op)
+            | Self::Neql(_, op) => Some(op),
+        }
+    }
+
+    fn with_operand(self, op: Operand) -> Self {
+        match self {
+            Self::Inp(_) | Self::Clear(_) => self,
+            Self::Add(reg, _) => Self::Add(reg, op),
+            Self::Mul(reg, _) => Self::Mul(reg, op),
+            Self::Div(reg, _) => Self::Div(reg, op),
+            Self::Mod(reg, _) => Self::Mod(reg, op),
+            Self::Eql(reg, _) => Self::Eql(reg, op),
+            Self::Set(reg, _) => Self::Set(reg, op),
+            Self::Neql(reg, _) => Self::Neql(reg, op),
+        }
+    }
+
+    fn discards_register(&self) -> Option<Register> {
+        match self {
+            Self::Inp(reg) => Some(*reg),
+            Self::Clear(reg) => Some(*reg),
+            Self::Set(reg, Operand::Value(_)) => Some(*reg),
+            Self::Set(reg1, Operand::Variable(reg2)) if reg1 != reg2 => Some(*reg1),
+            _ => None,
+        }
+    }
+
+    fn uses_register_val(&self, reg: Register) -> bool {
+        match self {
+            &Self::Add(reg1, Operand::Variable(reg2))
+            | &Self::Mul(reg1, Operand::Variable(reg2))
+            | &Self::Div(reg1, Operand::Variable(reg2))
+            | &Self::Mod(reg1, Operand::Variable(reg2))
+            | &Self::Eql(reg1, Operand::Variable(reg2))
+            | &Self::Neql(reg1, Operand::Variable(reg2))
+                if reg1 == reg || reg2 == reg =>
+            {
+                true
+            }
+            &Self::Add(rego, _)
+            | &Self::Mul(rego, _)
+            | &Self::Div(rego, _)
+            | &Self::Mod(rego, _)
+            | &Self::Eql(rego, _)
+            | &Self::Set(_, Operand::Variable(rego))
+            | &Self::Neql(rego, _)
+                if rego == reg =>
+            {
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Alu {
+    pub fn reg(&self, register: Register) -> i64 {
+        match register {
+            Register::X => self.x,
+            Register::Y => self.y,
+            Register::Z => self.z,
+            Register::W => self.w,
+        }
+    }
+
+    pub fn reg_mut(&mut self, register: Register) -> &mut i64 {
+        match register {
+            Register::X => &mut self.x,
+            Register::Y => &mut self.y,
+            Register::Z => &mut self.z,
+            Register::W => &mut self.w,
+        }
+    }
+
+    fn op_to_val(&self, op: Operand) -> i64 {
+        match op {
+            Operand::Value(v) => v,
+            Operand::Variable(r) => self.reg(r),
+        }
+    }
+
+    pub fn run_instruction(
+        &mut self,
+        instruction: Instruction,
+        input: &mut Option<i64>,
+    ) -> Result<()> {
+        use Instruction::*;
+        match instruction {
+            Inp(reg) => {
+                *self.reg_mut(reg) = input
+                    .take()
+                    .ok_or_else(|| anyhow!("Inp received in ALU with no input"))?;
+            }
+            Add(reg, op) => {
+                *self.reg_mut(reg) = self.reg(reg) + self.op_to_val(op);
+            }
+            Mul(reg, op) => {
+                *self.reg_mut(reg) = self.reg(reg) * self.op_to_val(op);
+            }
+            Div(reg, op) => {
+                *self.reg_mut(reg) = self
+                    .reg(reg)
+                    .checked_div(self.op_to_val(op))
+                    .ok_or_else(|| anyhow!("Division by zero in {}", instruction))?;
+            }
+            Mod(reg, op) => {
+                *self.reg_mut(reg) = self
+                    .reg(reg)
+                    .checked_rem(self.op_to_val(op))
+                    .ok_or_else(|| anyhow!("Modulo by zero in {}", instruction))?;
+            }
+            Eql(reg, op) => {
+                *self.reg_mut(reg) = (self.reg(reg) == self.op_to_val(op)) as i64;
+            }
+            Clear(reg) => {
+                *self.reg_mut(reg) = 0;
+            }
+            Set(reg, op) => *self.reg_mut(reg) = self.op_to_val(op),
+            Neql(reg, op) => {
+                *self.reg_mut(reg) = (self.reg(reg) != self.op_to_val(op)) as i64;
+            }
+        }
+        Ok(())
+    }
+
+    // Returns remaining instructions
+    pub fn run_for_input<'a>(
+        &mut self,
+        instructions: &'a [Instruction],
+        input: i64,
+    ) -> &'a [Instruction] {
+        let mut input = Some(input);
+        for (i, ins) in instructions.iter().enumerate() {
+            if input.is_none()
+                && let Instruction::Inp(_) = ins
+            {
+                return &instructions[i..];
+            }
+            let _ = self.run_instruction(*ins, &mut input);
+        }
+        &[]
+    }
+}
+
+// Something that can run a program one input at a time, starting at
+// instruction `pc` and stopping before the next inp, which it returns
+pub trait Runner: Sync {
+    fn run_for_input(&self, alu: &mut Alu, pc: usize, input: i64) -> usize;
+}
+
+impl Runner for [Instruction] {
+    fn run_for_input(&self, alu: &mut Alu, pc: usize, input: i64) -> usize {
+        self.len() - alu.run_for_input(&self[pc..], input).len()
+    }
+}
+
+// Inputs picked at random from the range, `count` at a time
+pub fn random_inputs(
+    range: RangeInclusive<i64>,
+    count: usize,
+    seed: u64,
+) -> impl Iterator<Item = Vec<i64>> {
+    let span = (range.end() - range.start() + 1) as u64;
+    let mut rng = Rng::new(seed);
+    let mut next = move || range.start() + rng.below(span) as i64;
+    std::iter::repeat_with(move || (0..count).map(|_| next()).collect())
+}
+
+// Runs the whole program, which has to read exactly all of the inputs
+pub fn execute(instructions: &[Instruction], inputs: &[i64]) -> Result<Alu> {
+    let mut alu = Alu::default();
+    let mut inputs = inputs.iter();
+    for &instruction in instructions {
+        let mut input = match instruction {
+            Instruction::Inp(_) => inputs.next().copied(),
+            _ => None,
+        };
+        alu.run_instruction(instruction, &mut input)?;
+    }
+    match inputs.len() {
+        0 => Ok(alu),
+        n => Err(anyhow!("{} inputs left unread", n)),
+    }
+}
+
+pub fn parse_program(file: &[u8]) -> Result<Vec<Instruction>> {
+    fn parse_register(input: &[u8]) -> IResult<&[u8], Register> {
+        let (rest, sign) = alt((tag(b"x"), tag(b"y"), tag(b"z"), tag(b"w")))(input)?;
+        let reg = match sign[0] {
+            b'x' => Register::X,
+            b'y' => Register::Y,
+            b'z' => Register::Z,
+            b'w' => Register::W,
+            _ => unreachable!(),
+        };
+        Ok((rest, reg))
+    }
+    fn parse_operand(input: &[u8]) -> IResult<&[u8], Operand> {
+        if let Ok((rest, num)) = parse_signed(input) {
+            Ok((rest, Operand::Value(num)))
+        } else {
+            parse_register(input).map(|t| (t.0, Operand::Variable(t.1)))
+        }
+    }
+    let single_operand = |text| -> IResult<&[u8], Instruction> {
+        let opcode = alt((tag(b"inp"), tag(b"clear")));
+        let (rest, (op, reg)) = separated_pair(opcode, tag(b" "), parse_register)(text)?;
+        match op {
+            b"inp" => Ok((rest, Instruction::Inp(reg))),
+            b"clear" => Ok((rest, Instruction::Clear(reg))),
+            _ => unreachable!(),
+        }
+    };
+    let dual_operand = |text| -> IResult<&[u8], Instruction> {
+        let opcode = alt((
+            tag(b"add"),
+            tag(b"mul"),
+            tag(b"div"),
+            tag(b"mod"),
+            tag(b"eql"),
+            tag(b"set"),
+            tag(b"neql"),
+        ));
+        let operands = separated_pair(parse_register, tag(b" "), parse_operand);
+        let (rest, (op, arg)) = separated_pair(opcode, tag(b" "), operands)(text)?;
+        match op {
+            b"add" => Ok((rest, Instruction::Add(arg.0, arg.1))),
+            b"mul" => Ok((rest, Instruction::Mul(arg.0, arg.1))),
+            b"div" => Ok((rest, Instruction::Div(arg.0, arg.1))),
+            b"mod" => Ok((rest, Instruction::Mod(arg.0, arg.1))),
+            b"eql" => Ok((rest, Instruction::Eql(arg.0, arg.1))),
+            b"set" => Ok((rest, Instruction::Set(arg.0, arg.1))),
+            b"neql" => Ok((rest, Instruction::Neql(arg.0, arg.1))),
+            _ => unreachable!(),
+        }
+    };
+    // clear, set and neql only come out of the optimiser, they're accepted so dumps parse back
+    let parse_instruction = alt((single_operand, dual_operand));
+
+    whole_input(lines(parse_instruction))(file).diagnose(file)
+}
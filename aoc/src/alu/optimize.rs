@@ -0,0 +1,439 @@
+use std::ops::RangeInclusive;
+
+use anyhow::{Context, Result, bail};
+
+use super::{Alu, Instruction, Operand, Register, execute, random_inputs};
+
+const REGISTERS: [Register; 4] = [Register::W, Register::X, Register::Y, Register::Z];
+
+type Live = [bool; 4];
+
+pub trait Pass {
+    fn name(&self) -> &'static str;
+    // Returns whether the program changed
+    fn run(&self, program: &mut Vec<Instruction>) -> bool;
+}
+
+// Drops instructions that leave their register as it was
+pub struct RemoveNoops;
+
+// Tracks registers with known values (all start at 0) and folds them into
+// operands, replacing instructions whose result is known with `set`/`clear`
+// and dropping the ones that store what the register already holds
+pub struct ConstantPropagation;
+
+// Peephole rewrites: `mul r 0` to `clear r`, `clear r` + `add r x` to
+// `set r x`, `eql r x` + `eql r 0` to `neql r x` and the like
+pub struct AlgebraicSimplification;
+
+// Removes instructions writing registers nobody reads afterwards
+pub struct DeadStoreElimination {
+    pub live_out: Vec<Register>,
+}
+
+// Clears the registers that are dead at each `inp` right after their last
+// use, so that ALU states between inputs compare equal when only dead
+// registers differ, which is what memoising searches like day24's key on
+pub struct ClearDeadRegisters {
+    pub live_out: Vec<Register>,
+}
+
+// Runs the original and optimised programs on the same random inputs and
+// compares the registers that matter
+pub struct EquivalenceChecker {
+    pub live_out: Vec<Register>,
+    pub inputs: RangeInclusive<i64>,
+    pub trials: usize,
+    pub seed: u64,
+}
+
+pub struct Pipeline {
+    // repeated until none of them changes anything
+    pub passes: Vec<Box<dyn Pass>>,
+    // run once at the end
+    pub cleanups: Vec<Box<dyn Pass>>,
+    pub checker: EquivalenceChecker,
+}
+
+fn live_set(registers: &[Register]) -> Live {
+    let mut live = [false; 4];
+    for &reg in registers {
+        live[reg as usize] = true;
+    }
+    live
+}
+
+// Liveness before `ins` given liveness after it
+fn transfer(ins: &Instruction, live: &mut Live) {
+    if ins.discards_register().is_some() {
+        live[ins.target() as usize] = false;
+    }
+    for reg in REGISTERS {
+        if ins.uses_register_val(reg) {
+            live[reg as usize] = true;
+        }
+    }
+}
+
+impl Pass for RemoveNoops {
+    fn name(&self) -> &'static str {
+        "remove no-ops"
+    }
+
+    fn run(&self, program: &mut Vec<Instruction>) -> bool {
+        use Instruction::*;
+        use Operand::*;
+        let old_len = program.len();
+        program.retain(|i| match *i {
+            Add(_, Value(0)) | Mul(_, Value(1)) | Div(_, Value(1)) => false,
+            Set(reg, Variable(src)) => reg != src,
+            _ => true,
+        });
+        old_len != program.len()
+    }
+}
+
+impl Pass for ConstantPropagation {
+    fn name(&self) -> &'static str {
+        "constant propagation"
+    }
+
+    fn run(&self, program: &mut Vec<Instruction>) -> bool {
+        use Instruction::*;
+        let mut known = [Some(0); 4];
+        let mut changed = false;
+        let mut result = Vec::with_capacity(program.len());
+        for &ins in program.iter() {
+            let target = ins.target() as usize;
+            let operand = ins.operand().map(|op| match op {
+                Operand::Variable(reg) => known[reg as usize].map_or(op, Operand::Value),
+                Operand::Value(_) => op,
+            });
+            // division by a known zero is left for the interpreter to report
+            let folded_operand = match (ins, operand) {
+                (Div(..) | Mod(..), Some(Operand::Value(0))) => ins,
+                (_, Some(op)) => ins.with_operand(op),
+                _ => ins,
+            };
+            let value = match (ins, known[target], operand) {
+                (Inp(_), _, _) => None,
+                (Clear(_), _, _) => Some(0),
+                (Set(..), _, Some(Operand::Value(v))) => Some(v),
+                (Mul(..), Some(0), _) => Some(0),
+                (_, Some(current), Some(Operand::Value(v))) => {
+                    let mut alu = Alu::default();
+                    *alu.reg_mut(ins.target()) = current;
+                    let evaluated = ins.with_operand(Operand::Value(v));
+                    alu.run_instruction(evaluated, &mut None)
+                        .ok()
+                        .map(|_| alu.reg(ins.target()))
+                }
+                _ => None,
+            };
+            let rewritten = match value {
+                _ if matches!(ins, Inp(_)) => ins,
+                Some(v) if known[target] == Some(v) => {
+                    changed = true;
+                    continue;
+                }
+                Some(0) => Clear(ins.target()),
+                Some(v) => Set(ins.target(), Operand::Value(v)),
+                None => folded_operand,
+            };
+            changed |= rewritten != ins;
+            known[target] = value;
+            result.push(rewritten);
+        }
+        *program = result;
+        changed
+    }
+}
+
+impl Pass for AlgebraicSimplification {
+    fn name(&self) -> &'static str {
+        "algebraic simplification"
+    }
+
+    fn run(&self, program: &mut Vec<Instruction>) -> bool {
+        use Instruction::*;
+        use Operand::*;
+        let mut changed = false;
+        for ins in program.iter_mut() {
+            let simplified = match *ins {
+                Mul(reg, Value(0)) | Mod(reg, Value(1 | -1)) | Set(reg, Value(0)) => Clear(reg),
+                Neql(reg, Variable(src)) if reg == src => Clear(reg),
+                Eql(reg, Variable(src)) if reg == src => Set(reg, Value(1)),
+                other => other,
+            };
+            changed |= simplified != *ins;
+            *ins = simplified;
+        }
+        let mut i = 0;
+        while i + 1 < program.len() {
+            let merged = match (program[i], program[i + 1]) {
+                (Clear(reg1), Add(reg2, op)) if reg1 == reg2 && op != Variable(reg1) => {
+                    Some(Set(reg1, op))
+                }
+                (Eql(reg1, op), Eql(reg2, Value(0))) if reg1 == reg2 => Some(Neql(reg1, op)),
+                (Neql(reg1, op), Eql(reg2, Value(0))) if reg1 == reg2 => Some(Eql(reg1, op)),
+                _ => None,
+            };
+            if let Some(merged) = merged {
+                program[i] = merged;
+                program.remove(i + 1);
+                changed = true;
+            }
+            i += 1;
+        }
+        changed
+    }
+}
+
+impl Pass for DeadStoreElimination {
+    fn name(&self) -> &'static str {
+        "dead store elimination"
+    }
+
+    fn run(&self, program: &mut Vec<Instruction>) -> bool {
+        let mut live = live_set(&self.live_out);
+        let mut keep = vec![true; program.len()];
+        for (i, ins) in program.iter().enumerate().rev() {
+            // an unused inp still has to consume its input
+            if !live[ins.target() as usize] && !matches!(ins, Instruction::Inp(_)) {
+                keep[i] = false;
+                continue;
+            }
+            transfer(ins, &mut live);
+        }
+        let mut keep = keep.into_iter();
+        let old_len = program.len();
+        program.retain(|_| keep.next().unwrap());
+        old_len != program.len()
+    }
+}
+
+impl Pass for ClearDeadRegisters {
+    fn name(&self) -> &'static str {
+        "clear dead registers"
+    }
+
+    fn run(&self, program: &mut Vec<Instruction>) -> bool {
+        let mut live_before = vec![live_set(&self.live_out); program.len() + 1];
+        for (i, ins) in program.iter().enumerate().rev() {
+            let mut live = live_before[i + 1];
+            transfer(ins, &mut live);
+            live_before[i] = live;
+        }
+
+        let mut clears = Vec::new();
+        for (i, ins) in program.iter().enumerate() {
+            if !matches!(ins, Instruction::Inp(_)) {
+                continue;
+            }
+            for reg in REGISTERS
+                .into_iter()
+                .filter(|&r| !live_before[i][r as usize])
+            {
+                let touches = |ins: &Instruction| ins.target() == reg || ins.uses_register_val(reg);
+                // untouched since the start means it still holds 0
+                let Some(last) = program[..i].iter().rposition(touches) else {
+                    continue;
+                };
+                if program[last] != Instruction::Clear(reg) {
+                    clears.push((last + 1, reg));
+                }
+            }
+        }
+        clears.sort_unstable_by_key(|&(pos, reg)| (pos, reg as usize));
+        clears.dedup();
+        for &(pos, reg) in clears.iter().rev() {
+            program.insert(pos, Instruction::Clear(reg));
+        }
+        !clears.is_empty()
+    }
+}
+
+impl EquivalenceChecker {
+    fn outputs(&self, alu: &Alu) -> Vec<i64> {
+        self.live_out.iter().map(|&reg| alu.reg(reg)).collect()
+    }
+
+    pub fn check(&self, original: &[Instruction], optimized: &[Instruction]) -> Result<()> {
+        let count = original
+            .iter()
+            .filter(|i| matches!(i, Instruction::Inp(_)))
+            .count();
+        let trials = random_inputs(self.inputs.clone(), count, self.seed).take(self.trials);
+        for inputs in trials {
+            // inputs the original can't run on don't tell us anything
+            let Ok(expected) = execute(original, &inputs) else {
+                continue;
+            };
+            let got = execute(optimized, &inputs)
+                .with_context(|| format!("Optimised program failed on inputs {:?}", inputs))?;
+            if self.outputs(&expected) != self.outputs(&got) {
+                bail!(
+                    "Optimised program differs on inputs {:?}: expected {}, got {}",
+                    inputs,
+                    expected,
+                    got
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Pipeline {
+    // The usual passes, keeping only the registers in `live_out` intact
+    pub fn new(live_out: &[Register]) -> Self {
+        let live_out = live_out.to_vec();
+        Pipeline {
+            passes: vec![
+                Box::new(RemoveNoops),
+                Box::new(AlgebraicSimplification),
+                Box::new(ConstantPropagation),
+                Box::new(DeadStoreElimination {
+                    live_out: live_out.clone(),
+                }),
+            ],
+            cleanups: vec![Box::new(ClearDeadRegisters {
+                live_out: live_out.clone(),
+            })],
+            checker: EquivalenceChecker {
+                live_out,
+                inputs: 1..=9,
+                trials: 64,
+                seed: 0x2021_1224,
+            },
+        }
+    }
+
+    // Checks the program against the original after every pass that changed
+    // it, so a miscompilation names the pass responsible
+    pub fn run(&self, original: &[Instruction]) -> Result<Vec<Instruction>> {
+        let mut program = original.to_vec();
+        let apply = |pass: &dyn Pass, program: &mut Vec<Instruction>| -> Result<bool> {
+            let changed = pass.run(program);
+            if changed {
+                self.checker
+                    .check(original, program)
+                    .with_context(|| format!("Pass \"{}\" broke the program", pass.name()))?;
+            }
+            Ok(changed)
+        };
+        loop {
+            let mut changed = false;
+            for pass in self.passes.iter() {
+                changed |= apply(pass.as_ref(), &mut program)?;
+            }
+            if !changed {
+                break;
+            }
+        }
+        for pass in self.cleanups.iter() {
+            apply(pass.as_ref(), &mut program)?;
+        }
+        Ok(program)
+    }
+}
+
+// MONAD only cares about z
+pub fn optimize_instructions(instructions: Vec<Instruction>) -> Result<Vec<Instruction>> {
+    Pipeline::new(&[Register::Z]).run(&instructions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::alu::parse_program;
+
+    fn program(text: &str) -> Vec<Instruction> {
+        parse_program(text.as_bytes()).unwrap()
+    }
+
+    fn listing(program: &[Instruction]) -> String {
+        program.iter().map(|i| format!("{}\n", i)).collect()
+    }
+
+    #[test]
+    fn single_passes() {
+        let mut p = program("inp w\nmul w 1\nadd x 0\ndiv y 1\nadd z w\n");
+        assert!(RemoveNoops.run(&mut p));
+        assert_eq!(listing(&p), "inp w\nadd z w\n");
+        assert!(!RemoveNoops.run(&mut p));
+
+        let mut p = program("mul x 0\nadd x 5\ninp w\neql w x\neql w 0\nmul y 0\nadd y w\n");
+        assert!(AlgebraicSimplification.run(&mut p));
+        assert_eq!(listing(&p), "set x 5\ninp w\nneql w x\nset y w\n");
+
+        let mut p = program("add x 3\nmul x 2\ninp w\nadd w x\nadd y 0\nmul z 7\n");
+        assert!(ConstantPropagation.run(&mut p));
+        assert_eq!(listing(&p), "set x 3\nset x 6\ninp w\nadd w 6\n");
+
+        let mut p = program("set x 3\nset x 6\ninp w\nadd w 6\nadd y w\nadd z x\n");
+        assert!(
+            DeadStoreElimination {
+                live_out: vec![Register::Z]
+            }
+            .run(&mut p)
+        );
+        assert_eq!(listing(&p), "set x 6\ninp w\nadd z x\n");
+    }
+
+    #[test]
+    fn clears_dead_registers() {
+        let mut p = program("inp w\nset x w\nadd z x\ninp w\nadd z w\n");
+        let pass = ClearDeadRegisters {
+            live_out: vec![Register::Z],
+        };
+        assert!(pass.run(&mut p));
+        assert_eq!(
+            listing(&p),
+            "inp w\nset x w\nclear w\nadd z x\nclear x\ninp w\nadd z w\n"
+        );
+    }
+
+    #[test]
+    fn optimizes_monad_block() {
+        let block = concat!(
+            "inp w\nmul x 0\nadd x z\nmod x 26\ndiv z 1\nadd x 12\neql x w\neql x 0\n",
+            "mul y 0\nadd y 25\nmul y x\nadd y 1\nmul z y\nmul y 0\nadd y w\nadd y 4\n",
+            "mul y x\nadd z y\n",
+        );
+        let source = program(&block.repeat(3));
+        let optimized = optimize_instructions(source.clone()).unwrap();
+        assert!(optimized.len() < source.len());
+        assert!(!optimized.contains(&Instruction::Div(Register::Z, Operand::Value(1))));
+        let checker = EquivalenceChecker {
+            live_out: vec![Register::Z],
+            inputs: 1..=9,
+            trials: 200,
+            seed: 7,
+        };
+        checker.check(&source, &optimized).unwrap();
+    }
+
+    #[test]
+    fn catches_miscompilation() {
+        // the no-op removal as it used to be, keeping only the no-ops
+        struct Broken;
+        impl Pass for Broken {
+            fn name(&self) -> &'static str {
+                "broken"
+            }
+            fn run(&self, program: &mut Vec<Instruction>) -> bool {
+                use Instruction::*;
+                let old_len = program.len();
+                program
+                    .retain(|i| matches!(i, Mul(_, Operand::Value(1)) | Div(_, Operand::Value(1))));
+                old_len != program.len()
+            }
+        }
+        let mut pipeline = Pipeline::new(&[Register::Z]);
+        pipeline.passes = vec![Box::new(Broken)];
+        let source = program("inp w\nmul w 1\nadd z w\n");
+        let err = pipeline.run(&source).unwrap_err();
+        assert!(format!("{:#}", err).starts_with("Pass \"broken\" broke the program"));
+    }
+}
@@ -0,0 +1,465 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    io::{BufRead, Write},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use itertools::Itertools;
+
+use super::{
+    Alu, Instruction, Runner, compile::Compiled, optimize::optimize_instructions, parse_program,
+    random_inputs, symbolic::analyze,
+};
+
+const USAGE: &str = "\
+usage: aoc alu <program> [options] [inputs...]
+
+options:
+  --optimize        run the optimised program instead of the source one, only z is kept
+  --dump            print the optimised program and exit
+  --explain         print what the digits must satisfy for z to end up 0 and exit
+  --bench <runs>    time the interpreter against the compiled program on random digits
+  --trace           print the registers after every instruction
+  --break-inp       stop before every inp instruction (implies --interactive)
+  --digits <n>      use the decimal digits of n as the inputs
+  -i, --interactive start a debugger prompt, type `help` for its commands
+
+Without inputs on the command line they're read from stdin.";
+
+const HELP: &str = "\
+commands:
+  s, step [n]       run n instructions (default 1)
+  c, continue       run until a breakpoint, missing input or the end
+  b, break <n|inp>  toggle a breakpoint at instruction n, or before every inp
+  i, input <v>...   queue input values
+  r, regs           print the registers
+  l, list           print the instructions around the current one
+  reset             restart the program, keeping queued inputs and breakpoints
+  q, quit           exit";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint(usize),
+    NeedsInput(usize),
+    Finished,
+}
+
+// Steps through an ALU program one instruction at a time
+pub struct Debugger<'a> {
+    program: &'a [Instruction],
+    pc: usize,
+    alu: Alu,
+    inputs: VecDeque<i64>,
+    breakpoints: BTreeSet<usize>,
+    break_on_inp: bool,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(program: &'a [Instruction]) -> Self {
+        Debugger {
+            program,
+            pc: 0,
+            alu: Alu::default(),
+            inputs: VecDeque::new(),
+            breakpoints: BTreeSet::new(),
+            break_on_inp: false,
+        }
+    }
+
+    pub fn alu(&self) -> &Alu {
+        &self.alu
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn feed(&mut self, inputs: impl IntoIterator<Item = i64>) {
+        self.inputs.extend(inputs);
+    }
+
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.alu = Alu::default();
+    }
+
+    pub fn toggle_breakpoint(&mut self, at: usize) -> bool {
+        if !self.breakpoints.remove(&at) {
+            self.breakpoints.insert(at);
+        }
+        self.breakpoints.contains(&at)
+    }
+
+    pub fn toggle_break_on_inp(&mut self) -> bool {
+        self.break_on_inp = !self.break_on_inp;
+        self.break_on_inp
+    }
+
+    fn stop_reason(&self) -> Option<Stop> {
+        match self.program.get(self.pc) {
+            None => Some(Stop::Finished),
+            Some(Instruction::Inp(_)) if self.inputs.is_empty() => Some(Stop::NeedsInput(self.pc)),
+            _ => None,
+        }
+    }
+
+    fn is_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.pc)
+            || (self.break_on_inp && matches!(self.program.get(self.pc), Some(Instruction::Inp(_))))
+    }
+
+    // Runs a single instruction, calling `trace` with it and the resulting registers
+    pub fn step<F>(&mut self, mut trace: F) -> Result<Option<Stop>>
+    where
+        F: FnMut(usize, Instruction, &Alu),
+    {
+        if let Some(stop) = self.stop_reason() {
+            return Ok(Some(stop));
+        }
+        let instruction = self.program[self.pc];
+        let mut input = match instruction {
+            Instruction::Inp(_) => self.inputs.pop_front(),
+            _ => None,
+        };
+        self.alu.run_instruction(instruction, &mut input)?;
+        trace(self.pc, instruction, &self.alu);
+        self.pc += 1;
+        Ok(None)
+    }
+
+    // Runs until the end, a breakpoint or an inp with nothing queued.
+    // A breakpoint on the current instruction doesn't stop it from starting.
+    pub fn run<F>(&mut self, mut trace: F) -> Result<Stop>
+    where
+        F: FnMut(usize, Instruction, &Alu),
+    {
+        let mut first = true;
+        loop {
+            if !first && self.is_breakpoint() {
+                return Ok(Stop::Breakpoint(self.pc));
+            }
+            first = false;
+            if let Some(stop) = self.step(&mut trace)? {
+                return Ok(stop);
+            }
+        }
+    }
+}
+
+fn trace_line(out: &mut impl Write, pc: usize, instruction: Instruction, alu: &Alu) {
+    let _ = writeln!(out, "{:4}  {:<12} {}", pc, instruction.to_string(), alu);
+}
+
+fn report(out: &mut impl Write, debugger: &Debugger, stop: &Stop) -> Result<()> {
+    match stop {
+        Stop::Breakpoint(pc) => writeln!(out, "breakpoint at {}: {}", pc, debugger.program[*pc])?,
+        Stop::NeedsInput(pc) => writeln!(out, "{} is waiting for input", debugger.program[*pc])?,
+        Stop::Finished => writeln!(out, "program finished: {}", debugger.alu)?,
+    }
+    Ok(())
+}
+
+fn parse_values(args: &[&str]) -> Result<Vec<i64>> {
+    args.iter()
+        .map(|a| {
+            a.parse()
+                .with_context(|| format!("Invalid input value {:?}", a))
+        })
+        .collect()
+}
+
+fn run_all<R: Runner + ?Sized>(program: &R, inputs: &[Vec<i64>]) -> (Duration, Vec<Alu>) {
+    let start = Instant::now();
+    let results = inputs
+        .iter()
+        .map(|digits| {
+            let mut alu = Alu::default();
+            let mut pc = 0;
+            for &digit in digits {
+                pc = program.run_for_input(&mut alu, pc, digit);
+            }
+            alu
+        })
+        .collect();
+    (start.elapsed(), results)
+}
+
+fn benchmark(program: &[Instruction], runs: usize) -> Result<()> {
+    let count = program
+        .iter()
+        .filter(|i| matches!(i, Instruction::Inp(_)))
+        .count();
+    let inputs: Vec<_> = random_inputs(1..=9, count, 0x2021_1224)
+        .take(runs)
+        .collect();
+    let compiled = Compiled::new(program);
+    let (interpreted, expected) = run_all(program, &inputs);
+    let (native, got) = run_all(&compiled, &inputs);
+    if let Some(i) = (0..runs).find(|&i| expected[i] != got[i]) {
+        bail!(
+            "Compiled program differs on inputs {:?}: expected {}, got {}",
+            inputs[i],
+            expected[i],
+            got[i]
+        );
+    }
+    let per_run = |d: Duration| d.as_secs_f64() * 1e9 / runs.max(1) as f64;
+    println!(
+        "interpreter: {:?} ({:.0} ns/run)",
+        interpreted,
+        per_run(interpreted)
+    );
+    println!("compiled:    {:?} ({:.0} ns/run)", native, per_run(native));
+    println!(
+        "speedup:     {:.2}x",
+        interpreted.as_secs_f64() / native.as_secs_f64()
+    );
+    Ok(())
+}
+
+// Reads debugger commands until `quit` or the end of `commands`
+pub fn repl(debugger: &mut Debugger, commands: impl BufRead, mut out: impl Write) -> Result<()> {
+    write!(out, "> ")?;
+    out.flush()?;
+    for line in commands.lines() {
+        let line = line?;
+        let words: Vec<_> = line.split_whitespace().collect();
+        let result = match words.as_slice() {
+            [] => Ok(()),
+            ["q" | "quit"] => break,
+            ["h" | "help"] => writeln!(out, "{}", HELP).map_err(Into::into),
+            ["s" | "step", rest @ ..] => {
+                let count = match rest {
+                    [] => Ok(1),
+                    [n] => n.parse().context("Invalid step count"),
+                    _ => Err(anyhow!("step takes at most one argument")),
+                };
+                count.and_then(|count: usize| {
+                    for _ in 0..count {
+                        if let Some(stop) =
+                            debugger.step(|pc, ins, alu| trace_line(&mut out, pc, ins, alu))?
+                        {
+                            return report(&mut out, debugger, &stop);
+                        }
+                    }
+                    Ok(())
+                })
+            }
+            ["c" | "continue"] => debugger
+                .run(|pc, ins, alu| trace_line(&mut out, pc, ins, alu))
+                .and_then(|stop| report(&mut out, debugger, &stop)),
+            ["b" | "break", "inp"] => {
+                let on = debugger.toggle_break_on_inp();
+                writeln!(out, "break on inp {}", if on { "on" } else { "off" }).map_err(Into::into)
+            }
+            ["b" | "break", at] => {
+                at.parse()
+                    .context("Invalid instruction index")
+                    .and_then(|at: usize| {
+                        if at >= debugger.program.len() {
+                            bail!("Program only has {} instructions", debugger.program.len());
+                        }
+                        let on = debugger.toggle_breakpoint(at);
+                        writeln!(
+                            out,
+                            "breakpoint {} {}",
+                            at,
+                            if on { "set" } else { "cleared" }
+                        )?;
+                        Ok(())
+                    })
+            }
+            ["i" | "input", values @ ..] => parse_values(values).map(|v| debugger.feed(v)),
+            ["r" | "regs"] => writeln!(out, "{}", debugger.alu).map_err(Into::into),
+            ["l" | "list"] => {
+                let from = debugger.pc.saturating_sub(3);
+                for (i, ins) in debugger.program.iter().enumerate().skip(from).take(7) {
+                    let marker = if i == debugger.pc { "->" } else { "  " };
+                    writeln!(out, "{} {:4}  {}", marker, i, ins)?;
+                }
+                Ok(())
+            }
+            ["reset"] => {
+                debugger.reset();
+                Ok(())
+            }
+            _ => Err(anyhow!("Unknown command, type `help` for the list")),
+        };
+        if let Err(e) = result {
+            writeln!(out, "error: {}", e)?;
+        }
+        write!(out, "> ")?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+// Entry point of the `aoc alu` subcommand
+pub fn run(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut inputs = Vec::new();
+    let mut bench = None;
+    let (mut optimize, mut dump, mut explain, mut trace, mut interactive, mut break_inp) =
+        (false, false, false, false, false, false);
+    let mut args = args.iter().map(String::as_str);
+    while let Some(arg) = args.next() {
+        match arg {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "--optimize" => optimize = true,
+            "--dump" => dump = true,
+            "--explain" => explain = true,
+            "--trace" => trace = true,
+            "-i" | "--interactive" => interactive = true,
+            "--break-inp" => break_inp = true,
+            "--bench" => {
+                let runs = args.next().context("--bench needs a number of runs")?;
+                bench = Some(runs.parse().context("Invalid number of runs")?);
+            }
+            "--digits" => {
+                let digits = args.next().context("--digits needs a number")?;
+                for ch in digits.bytes() {
+                    if !ch.is_ascii_digit() {
+                        bail!("--digits takes only decimal digits, got {:?}", digits);
+                    }
+                    inputs.push((ch - b'0') as i64);
+                }
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => inputs.extend(parse_values(&[arg])?),
+        }
+    }
+    let path = path.ok_or_else(|| anyhow!("Missing program path\n\n{}", USAGE))?;
+    let file = std::fs::read(path).with_context(|| format!("Failed reading {}", path))?;
+    let source = parse_program(&file)?;
+
+    if dump {
+        for (i, ins) in optimize_instructions(source)?.iter().enumerate() {
+            println!("{:4}  {}", i, ins);
+        }
+        return Ok(());
+    }
+    let program = match optimize {
+        true => optimize_instructions(source)?,
+        false => source,
+    };
+    if let Some(runs) = bench {
+        return benchmark(&program, runs);
+    }
+    if explain {
+        let analysis = analyze(&program, 1..=9)
+            .context("The program is too irregular for the symbolic analysis")?;
+        println!("{}", analysis.explain());
+        if let (Some(max), Some(min)) = (analysis.max_input(), analysis.min_input()) {
+            println!("largest: {}", max.iter().join(""));
+            println!("smallest: {}", min.iter().join(""));
+        }
+        return Ok(());
+    }
+
+    let mut debugger = Debugger::new(&program);
+    debugger.feed(inputs);
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if interactive || break_inp {
+        if break_inp {
+            debugger.toggle_break_on_inp();
+        }
+        return repl(&mut debugger, stdin.lock(), stdout.lock());
+    }
+
+    if debugger.inputs.is_empty() {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut stdin.lock(), &mut text)?;
+        debugger.feed(parse_values(&text.split_whitespace().collect::<Vec<_>>())?);
+    }
+    let mut out = stdout.lock();
+    let stop = debugger.run(|pc, ins, alu| {
+        if trace {
+            trace_line(&mut out, pc, ins, alu);
+        }
+    })?;
+    match stop {
+        Stop::Finished => writeln!(out, "{}", debugger.alu())?,
+        _ => bail!("Ran out of inputs at instruction {}", debugger.pc()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // binary conversion example from the puzzle
+    const BINARY: &[u8] = concat!(
+        "inp w\n",
+        "add z w\n",
+        "mod z 2\n",
+        "div w 2\n",
+        "add y w\n",
+        "mod y 2\n",
+        "div w 2\n",
+        "add x w\n",
+        "mod x 2\n",
+        "div w 2\n",
+        "mod w 2\n",
+    )
+    .as_bytes();
+
+    #[test]
+    fn runs_to_completion() {
+        let program = parse_program(BINARY).unwrap();
+        let mut debugger = Debugger::new(&program);
+        assert_eq!(debugger.run(|_, _, _| ()).unwrap(), Stop::NeedsInput(0));
+        debugger.feed([13]);
+        let mut steps = 0;
+        assert_eq!(debugger.run(|_, _, _| steps += 1).unwrap(), Stop::Finished);
+        assert_eq!(steps, program.len());
+        assert_eq!(
+            *debugger.alu(),
+            Alu {
+                w: 1,
+                x: 1,
+                y: 0,
+                z: 1
+            }
+        );
+    }
+
+    #[test]
+    fn breakpoints() {
+        let program = parse_program(BINARY).unwrap();
+        let mut debugger = Debugger::new(&program);
+        debugger.feed([6]);
+        debugger.toggle_breakpoint(4);
+        assert_eq!(debugger.run(|_, _, _| ()).unwrap(), Stop::Breakpoint(4));
+        assert_eq!(debugger.alu().z, 0);
+        assert_eq!(debugger.run(|_, _, _| ()).unwrap(), Stop::Finished);
+
+        debugger.reset();
+        debugger.toggle_break_on_inp();
+        // starting on a breakpoint doesn't stop immediately
+        assert_eq!(debugger.run(|_, _, _| ()).unwrap(), Stop::NeedsInput(0));
+    }
+
+    #[test]
+    fn repl_session() {
+        let program = parse_program(BINARY).unwrap();
+        let mut debugger = Debugger::new(&program);
+        let commands = "input 5\nstep 2\nb 7\nc\nregs\nbogus\nc\nq\nstep\n";
+        let mut out = Vec::new();
+        repl(&mut debugger, commands.as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let traced = format!("{:4}  {:<12} {}", 1, "add z w", "w=5 x=0 y=0 z=5");
+        assert!(out.contains(&traced));
+        assert!(out.contains("breakpoint 7 set"));
+        assert!(out.contains("breakpoint at 7: add x w"));
+        assert!(out.contains("w=1 x=0 y=0 z=1"));
+        assert!(out.contains("error: Unknown command"));
+        assert!(out.contains("program finished: w=0 x=1 y=0 z=1"));
+        assert_eq!(debugger.pc(), program.len());
+    }
+}
@@ -0,0 +1,645 @@
+use std::{fmt::Display, ops::RangeInclusive, rc::Rc};
+
+use itertools::Itertools;
+
+use super::{Instruction, Operand, Register, execute};
+
+// Forking on every undecided `eql` can blow up on programs that aren't
+// shaped like MONAD, give up instead of eating all the memory
+const MAX_STATES: usize = 1 << 16;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Expr {
+    Const(i64),
+    Input(usize),
+    Add(Value, Value),
+    Mul(Value, Value),
+    Div(Value, Value),
+    Mod(Value, Value),
+}
+
+// An expression together with the interval of values it can take
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Value {
+    expr: Rc<Expr>,
+    lo: i64,
+    hi: i64,
+}
+
+// w[left] == w[right] + offset, or != when `equal` is false
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Constraint {
+    pub left: usize,
+    pub right: usize,
+    pub offset: i64,
+    pub equal: bool,
+}
+
+// One way through the program that ends with z == 0
+#[derive(Clone, Debug)]
+pub struct Path {
+    pub constraints: Vec<Constraint>,
+    ranges: Vec<(i64, i64)>,
+}
+
+#[derive(Debug)]
+pub struct Analysis {
+    pub inputs: usize,
+    pub paths: Vec<Path>,
+}
+
+#[derive(Clone)]
+struct State {
+    regs: [Value; 4],
+    path: Path,
+    // set when the path relies on a condition we can't express as a Constraint
+    opaque: bool,
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Const(v) => write!(f, "{}", v),
+            Expr::Input(i) => write!(f, "w{}", i),
+            Expr::Add(l, r) => write!(f, "({} + {})", l, r),
+            Expr::Mul(l, r) => write!(f, "{} * {}", l, r),
+            Expr::Div(l, r) => write!(f, "({} / {})", l, r),
+            Expr::Mod(l, r) => write!(f, "({} % {})", l, r),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cmp = if self.equal { "==" } else { "!=" };
+        match self.offset {
+            0 => write!(f, "w{} {} w{}", self.left, cmp, self.right),
+            o if o < 0 => write!(f, "w{} {} w{} - {}", self.left, cmp, self.right, -o),
+            o => write!(f, "w{} {} w{} + {}", self.left, cmp, self.right, o),
+        }
+    }
+}
+
+impl Constraint {
+    fn holds(&self, digits: &[i64]) -> bool {
+        (digits[self.left] == digits[self.right] + self.offset) == self.equal
+    }
+}
+
+impl Value {
+    fn constant(v: i64) -> Self {
+        Value {
+            expr: Rc::new(Expr::Const(v)),
+            lo: v,
+            hi: v,
+        }
+    }
+
+    fn new(expr: Expr, lo: i64, hi: i64) -> Self {
+        match lo == hi {
+            true => Self::constant(lo),
+            false => Value {
+                expr: Rc::new(expr),
+                lo,
+                hi,
+            },
+        }
+    }
+
+    pub fn interval(&self) -> RangeInclusive<i64> {
+        self.lo..=self.hi
+    }
+
+    fn as_const(&self) -> Option<i64> {
+        (self.lo == self.hi).then_some(self.lo)
+    }
+
+    // `Input(i) + c` as (i, c)
+    fn as_input_offset(&self) -> Option<(usize, i64)> {
+        match &*self.expr {
+            Expr::Input(i) => Some((*i, 0)),
+            Expr::Add(l, r) => match (l.as_const(), r.as_const()) {
+                (None, Some(c)) => l.as_input_offset().map(|(i, off)| (i, off + c)),
+                (Some(c), None) => r.as_input_offset().map(|(i, off)| (i, off + c)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // `p * k + q` with a non-negative p and q in 0..k, as (p, q)
+    fn as_digits(&self, k: i64) -> Option<(&Value, &Value)> {
+        let Expr::Add(l, q) = &*self.expr else {
+            return None;
+        };
+        let Expr::Mul(p, scale) = &*l.expr else {
+            return None;
+        };
+        (scale.as_const() == Some(k) && p.lo >= 0 && q.lo >= 0 && q.hi < k).then_some((p, q))
+    }
+
+    fn add(&self, rhs: &Value) -> Value {
+        match (self.as_const(), rhs.as_const()) {
+            (Some(l), Some(r)) => return Value::constant(l + r),
+            (Some(0), _) => return rhs.clone(),
+            (_, Some(0)) => return self.clone(),
+            (Some(_), None) => return rhs.add(self),
+            _ => (),
+        }
+        let (lo, hi) = (
+            self.lo.saturating_add(rhs.lo),
+            self.hi.saturating_add(rhs.hi),
+        );
+        // fold constants into an existing `x + c`
+        if let (Expr::Add(x, c), Some(r)) = (&*self.expr, rhs.as_const())
+            && let Some(c) = c.as_const()
+        {
+            return Value::new(Expr::Add(x.clone(), Value::constant(c + r)), lo, hi);
+        }
+        Value::new(Expr::Add(self.clone(), rhs.clone()), lo, hi)
+    }
+
+    fn mul(&self, rhs: &Value) -> Value {
+        match (self.as_const(), rhs.as_const()) {
+            (Some(l), Some(r)) => return Value::constant(l * r),
+            (Some(0), _) | (_, Some(0)) => return Value::constant(0),
+            (Some(1), _) => return rhs.clone(),
+            (_, Some(1)) => return self.clone(),
+            (Some(_), None) => return rhs.mul(self),
+            _ => (),
+        }
+        let products = [
+            self.lo.saturating_mul(rhs.lo),
+            self.lo.saturating_mul(rhs.hi),
+            self.hi.saturating_mul(rhs.lo),
+            self.hi.saturating_mul(rhs.hi),
+        ];
+        let lo = *products.iter().min().unwrap();
+        let hi = *products.iter().max().unwrap();
+        Value::new(Expr::Mul(self.clone(), rhs.clone()), lo, hi)
+    }
+
+    // bound on the result of dividing by or taking the remainder of anything
+    fn magnitude(&self) -> i64 {
+        self.lo.saturating_abs().max(self.hi.saturating_abs())
+    }
+
+    fn div(&self, rhs: &Value) -> Option<Value> {
+        let Some(d) = rhs.as_const() else {
+            let bound = self.magnitude();
+            return Some(Value::new(
+                Expr::Div(self.clone(), rhs.clone()),
+                -bound,
+                bound,
+            ));
+        };
+        match (d, self.as_const()) {
+            (0, _) => return None,
+            (1, _) => return Some(self.clone()),
+            (_, Some(v)) => return Some(Value::constant(v / d)),
+            _ => (),
+        }
+        if let Some((p, _)) = self.as_digits(d) {
+            return Some(p.clone());
+        }
+        // truncating division by a constant is monotonic
+        let (lo, hi) = match d > 0 {
+            true => (self.lo / d, self.hi / d),
+            false => (self.hi / d, self.lo / d),
+        };
+        Some(Value::new(Expr::Div(self.clone(), rhs.clone()), lo, hi))
+    }
+
+    fn modulo(&self, rhs: &Value) -> Option<Value> {
+        let Some(d) = rhs.as_const() else {
+            let bound = self.magnitude();
+            return Some(Value::new(
+                Expr::Mod(self.clone(), rhs.clone()),
+                -bound,
+                bound,
+            ));
+        };
+        match (d, self.as_const()) {
+            (0, _) => return None,
+            (_, Some(v)) => return Some(Value::constant(v % d)),
+            _ => (),
+        }
+        if self.lo >= 0 && self.hi < d.abs() {
+            return Some(self.clone());
+        }
+        if let Some((_, q)) = self.as_digits(d) {
+            return Some(q.clone());
+        }
+        // the remainder takes the sign of the dividend
+        let lo = if self.lo >= 0 {
+            0
+        } else {
+            self.lo.max(1 - d.abs())
+        };
+        let hi = if self.hi <= 0 {
+            0
+        } else {
+            self.hi.min(d.abs() - 1)
+        };
+        Some(Value::new(Expr::Mod(self.clone(), rhs.clone()), lo, hi))
+    }
+
+    // Some(result) when the intervals decide it, None if it could go either way
+    fn eql(&self, rhs: &Value) -> Option<bool> {
+        if self.hi < rhs.lo || rhs.hi < self.lo {
+            Some(false)
+        } else if self.as_const().is_some() && self.as_const() == rhs.as_const() {
+            Some(true)
+        } else {
+            None
+        }
+    }
+}
+
+impl Path {
+    // Narrows the inputs' ranges, false if the constraint can't be satisfied
+    fn constrain(&mut self, constraint: Constraint) -> bool {
+        let Constraint {
+            left,
+            right,
+            offset,
+            equal,
+        } = constraint;
+        if equal {
+            let (l, r) = (self.ranges[left], self.ranges[right]);
+            let lo = l.0.max(r.0 + offset);
+            let hi = l.1.min(r.1 + offset);
+            if lo > hi {
+                return false;
+            }
+            self.ranges[left] = (lo, hi);
+            self.ranges[right] = (lo - offset, hi - offset);
+        }
+        self.constraints.push(constraint);
+        true
+    }
+
+    // Highest or lowest digits satisfying all the constraints, picked
+    // from the most significant one down
+    fn extreme(&self, highest: bool) -> Option<Vec<i64>> {
+        fn search(path: &Path, digits: &mut Vec<i64>, highest: bool) -> bool {
+            let i = digits.len();
+            if i == path.ranges.len() {
+                return true;
+            }
+            let (lo, hi) = path.ranges[i];
+            let candidates: Box<dyn Iterator<Item = i64>> = match highest {
+                true => Box::new((lo..=hi).rev()),
+                false => Box::new(lo..=hi),
+            };
+            for digit in candidates {
+                digits.push(digit);
+                let consistent = path
+                    .constraints
+                    .iter()
+                    .filter(|c| c.left.max(c.right) == i)
+                    .all(|c| c.holds(digits));
+                if consistent && search(path, digits, highest) {
+                    return true;
+                }
+                digits.pop();
+            }
+            false
+        }
+        let mut digits = Vec::with_capacity(self.ranges.len());
+        search(self, &mut digits, highest).then_some(digits)
+    }
+}
+
+impl Analysis {
+    pub fn max_input(&self) -> Option<Vec<i64>> {
+        self.paths.iter().filter_map(|p| p.extreme(true)).max()
+    }
+
+    pub fn min_input(&self) -> Option<Vec<i64>> {
+        self.paths.iter().filter_map(|p| p.extreme(false)).min()
+    }
+
+    // The conditions on the inputs for z to end up 0, one path per line
+    pub fn explain(&self) -> String {
+        if self.paths.is_empty() {
+            return "z can never be 0".to_owned();
+        }
+        let mut lines = Vec::new();
+        for (i, path) in self.paths.iter().enumerate() {
+            let conditions = match path.constraints.is_empty() {
+                true => "always".to_owned(),
+                false => path.constraints.iter().join(", "),
+            };
+            lines.push(format!("path {}: {}", i + 1, conditions));
+        }
+        lines.join("\n")
+    }
+}
+
+impl State {
+    // The branch where `l == r` is `equal`, None if that's impossible
+    fn branch(&self, l: &Value, r: &Value, equal: bool) -> Option<State> {
+        let mut branch = self.clone();
+        match (l.as_input_offset(), r.as_input_offset()) {
+            (Some((li, lc)), Some((ri, rc))) if li != ri => {
+                let constraint = Constraint {
+                    left: li,
+                    right: ri,
+                    offset: rc - lc,
+                    equal,
+                };
+                if !branch.path.constrain(constraint) {
+                    return None;
+                }
+            }
+            _ => branch.opaque = true,
+        }
+        Some(branch)
+    }
+}
+
+// Every register's interval before each instruction, over all the ways
+// through the program
+fn register_intervals(
+    program: &[Instruction],
+    digits: &RangeInclusive<i64>,
+) -> Vec<[(i64, i64); 4]> {
+    let mut regs = [(0i64, 0i64); 4];
+    let mut intervals = Vec::with_capacity(program.len());
+    for &ins in program {
+        intervals.push(regs);
+        let operand = |op| match op {
+            Operand::Value(v) => (v, v),
+            Operand::Variable(r) => regs[r as usize],
+        };
+        let (lo, hi) = regs[ins.target() as usize];
+        let magnitude = lo.saturating_abs().max(hi.saturating_abs());
+        regs[ins.target() as usize] = match ins {
+            Instruction::Inp(_) => (*digits.start(), *digits.end()),
+            Instruction::Add(_, op) => {
+                let (l, h) = operand(op);
+                (lo.saturating_add(l), hi.saturating_add(h))
+            }
+            Instruction::Mul(_, op) => {
+                let (l, h) = operand(op);
+                let products = [
+                    lo.saturating_mul(l),
+                    lo.saturating_mul(h),
+                    hi.saturating_mul(l),
+                    hi.saturating_mul(h),
+                ];
+                (
+                    *products.iter().min().unwrap(),
+                    *products.iter().max().unwrap(),
+                )
+            }
+            // no bigger than the dividend, and with its sign unless divided
+            // by something negative
+            Instruction::Div(_, op) if operand(op).0 < 0 => (-magnitude, magnitude),
+            Instruction::Div(..) | Instruction::Mod(..) => (
+                if lo < 0 { -magnitude } else { 0 },
+                if hi > 0 { magnitude } else { 0 },
+            ),
+            Instruction::Eql(..) | Instruction::Neql(..) => (0, 1),
+            Instruction::Clear(_) => (0, 0),
+            Instruction::Set(_, op) => operand(op),
+        };
+    }
+    intervals
+}
+
+// How much the rest of the program can shrink z at most, for each position.
+// z only gets smaller through `div z <const>`, anything else that could
+// bring it closer to 0 means no bound before it.
+fn shrink_bounds(program: &[Instruction], digits: &RangeInclusive<i64>) -> Vec<i64> {
+    let intervals = register_intervals(program, digits);
+    let mut bounds = vec![1i64; program.len() + 1];
+    for (i, ins) in program.iter().enumerate().rev() {
+        let (z_lo, z_hi) = intervals[i][Register::Z as usize];
+        let operand = |op| match op {
+            Operand::Value(v) => (v, v),
+            Operand::Variable(r) => intervals[i][r as usize],
+        };
+        bounds[i] = match ins {
+            Instruction::Div(Register::Z, Operand::Value(d)) => {
+                bounds[i + 1].saturating_mul(d.abs())
+            }
+            // unbounded if it could add something of the other sign
+            Instruction::Add(Register::Z, op) => match operand(*op) {
+                (lo, _) if z_lo >= 0 && lo >= 0 => bounds[i + 1],
+                (_, hi) if z_hi <= 0 && hi <= 0 => bounds[i + 1],
+                _ => i64::MAX,
+            },
+            // or multiply by 0
+            Instruction::Mul(Register::Z, op) => match operand(*op) {
+                (lo, hi) if lo >= 1 || hi <= -1 => bounds[i + 1],
+                _ => i64::MAX,
+            },
+            Instruction::Mod(Register::Z, _)
+            | Instruction::Eql(Register::Z, _)
+            | Instruction::Neql(Register::Z, _)
+            | Instruction::Clear(Register::Z)
+            | Instruction::Set(Register::Z, _)
+            | Instruction::Inp(Register::Z)
+            | Instruction::Div(Register::Z, Operand::Variable(_)) => i64::MAX,
+            _ => bounds[i + 1],
+        };
+    }
+    bounds
+}
+
+// Runs the program over symbolic inputs taken from `digits`, forking
+// whenever an equality could go either way, and keeps the paths where z
+// ends up 0. None if the program is beyond what this can reason about.
+pub fn analyze(program: &[Instruction], digits: RangeInclusive<i64>) -> Option<Analysis> {
+    let inputs = program
+        .iter()
+        .filter(|i| matches!(i, Instruction::Inp(_)))
+        .count();
+    let bounds = shrink_bounds(program, &digits);
+    let zero = Value::constant(0);
+    let mut states = vec![State {
+        regs: [zero.clone(), zero.clone(), zero.clone(), zero],
+        path: Path {
+            constraints: Vec::new(),
+            ranges: vec![(*digits.start(), *digits.end()); inputs],
+        },
+        opaque: false,
+    }];
+    let mut next_input = 0;
+
+    for (pc, &ins) in program.iter().enumerate() {
+        let mut next_states = Vec::with_capacity(states.len());
+        for mut state in states {
+            let operand = |state: &State, op| match op {
+                Operand::Value(v) => Value::constant(v),
+                Operand::Variable(r) => state.regs[r as usize].clone(),
+            };
+            use Instruction::*;
+            let value = match ins {
+                Inp(_) => {
+                    let (lo, hi) = state.path.ranges[next_input];
+                    Value::new(Expr::Input(next_input), lo, hi)
+                }
+                Add(r, op) => state.regs[r as usize].add(&operand(&state, op)),
+                Mul(r, op) => state.regs[r as usize].mul(&operand(&state, op)),
+                Div(r, op) => state.regs[r as usize].div(&operand(&state, op))?,
+                Mod(r, op) => state.regs[r as usize].modulo(&operand(&state, op))?,
+                Clear(_) => Value::constant(0),
+                Set(_, op) => operand(&state, op),
+                Eql(r, op) | Neql(r, op) => {
+                    let (l, rhs) = (state.regs[r as usize].clone(), operand(&state, op));
+                    let negate = matches!(ins, Neql(..));
+                    match l.eql(&rhs) {
+                        Some(equal) => Value::constant((equal != negate) as i64),
+                        None => {
+                            for equal in [true, false] {
+                                if let Some(mut branch) = state.branch(&l, &rhs, equal) {
+                                    branch.regs[r as usize] =
+                                        Value::constant((equal != negate) as i64);
+                                    next_states.push(branch);
+                                }
+                            }
+                            continue;
+                        }
+                    }
+                }
+            };
+            state.regs[ins.target() as usize] = value;
+            next_states.push(state);
+        }
+        if matches!(ins, Instruction::Inp(_)) {
+            next_input += 1;
+        }
+
+        // drop the paths where z is already too far from 0 to be divided back down
+        let bound = bounds[pc + 1];
+        next_states.retain(|s| {
+            let z = &s.regs[Register::Z as usize];
+            let nearest = match (z.lo > 0, z.hi < 0) {
+                (true, _) => z.lo,
+                (_, true) => z.hi.saturating_neg(),
+                _ => 0,
+            };
+            nearest < bound
+        });
+        if next_states.len() > MAX_STATES {
+            return None;
+        }
+        states = next_states;
+    }
+
+    let mut paths = Vec::new();
+    for state in states {
+        let z = &state.regs[Register::Z as usize];
+        if !z.interval().contains(&0) {
+            continue;
+        }
+        // a path we can't describe, or one where z only might be 0
+        if state.opaque || z.as_const() != Some(0) {
+            return None;
+        }
+        paths.push(state.path);
+    }
+    Some(Analysis { inputs, paths })
+}
+
+// Checks digits against the program by actually running it
+pub fn verify(program: &[Instruction], digits: &[i64]) -> bool {
+    execute(program, digits).is_ok_and(|alu| alu.z == 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::alu::parse_program;
+
+    // one MONAD block: `div z {div}`, `add x {check}` and `add y {offset}`
+    fn block(div: i64, check: i64, offset: i64) -> String {
+        format!(
+            concat!(
+                "inp w\nmul x 0\nadd x z\nmod x 26\ndiv z {}\nadd x {}\neql x w\neql x 0\n",
+                "mul y 0\nadd y 25\nmul y x\nadd y 1\nmul z y\nmul y 0\nadd y w\nadd y {}\n",
+                "mul y x\nadd z y\n",
+            ),
+            div, check, offset
+        )
+    }
+
+    fn monad(blocks: &[(i64, i64, i64)]) -> Vec<Instruction> {
+        let text: String = blocks.iter().map(|&(d, c, o)| block(d, c, o)).collect();
+        parse_program(text.as_bytes()).unwrap()
+    }
+
+    fn brute_force(program: &[Instruction], inputs: usize) -> Vec<Vec<i64>> {
+        (0..inputs)
+            .map(|_| 1..=9)
+            .multi_cartesian_product()
+            .filter(|digits| verify(program, digits))
+            .collect()
+    }
+
+    const BLOCKS: [(i64, i64, i64); 6] = [
+        (1, 12, 4),
+        (1, 11, 11),
+        (26, -3, 5),
+        (1, 14, 7),
+        (26, -10, 2),
+        (26, -9, 1),
+    ];
+
+    #[test]
+    fn derives_pair_constraints() {
+        let analysis = analyze(&monad(&BLOCKS), 1..=9).unwrap();
+        assert_eq!(analysis.inputs, 6);
+        assert_eq!(
+            analysis.explain(),
+            "path 1: w1 == w2 - 8, w3 == w4 + 3, w0 == w5 + 5"
+        );
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let blocks = [(1, 12, 4), (1, 11, 11), (26, -3, 5), (26, -9, 1)];
+        let program = monad(&blocks);
+        let analysis = analyze(&program, 1..=9).unwrap();
+        let valid = brute_force(&program, blocks.len());
+        assert!(!valid.is_empty());
+        assert_eq!(analysis.max_input().as_ref(), valid.iter().max());
+        assert_eq!(analysis.min_input().as_ref(), valid.iter().min());
+    }
+
+    #[test]
+    fn impossible_program() {
+        // the second block can never pop what the first one pushed
+        let analysis = analyze(&monad(&[(1, 12, 4), (26, -20, 5)]), 1..=9).unwrap();
+        assert!(analysis.paths.is_empty());
+        assert_eq!(analysis.max_input(), None);
+        assert_eq!(analysis.explain(), "z can never be 0");
+    }
+
+    #[test]
+    fn z_shrinking_without_div() {
+        let program = |text: &str| parse_program(text.as_bytes()).unwrap();
+        // every input works, z is wiped at the end
+        let analysis = analyze(&program("inp w\nadd z w\nmul z 0\n"), 1..=9).unwrap();
+        assert_eq!(analysis.max_input(), Some(vec![9]));
+        // these can get z to 0, so they mustn't be called impossible
+        for (text, inputs) in [
+            ("inp w\nadd z w\nadd z -5\n", 1),
+            ("inp w\nadd z w\ninp x\nmul x -1\nadd z x\n", 2),
+        ] {
+            let program = program(text);
+            assert!(!brute_force(&program, inputs).is_empty());
+            let analysis = analyze(&program, 1..=9);
+            assert!(analysis.is_none_or(|a| !a.paths.is_empty()), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use ndarray::{Array2, Axis, parallel::prelude::*};
+
+use super::{Offset, Rule, Space};
+
+// What neighbours past the edge of the grid are
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edges<T> {
+    Background(T),
+    // the grid is a torus, the other side is next to the edge
+    Wrap,
+}
+
+// A fixed grid of cells, updated a row per thread
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dense<T> {
+    cells: Array2<T>,
+    edges: Edges<T>,
+}
+
+impl<T: Copy> Dense<T> {
+    pub fn new(cells: Array2<T>, edges: Edges<T>) -> Self {
+        Dense { cells, edges }
+    }
+
+    pub fn cells(&self) -> &Array2<T> {
+        &self.cells
+    }
+
+    fn neighbour(&self, (row, col): (usize, usize), (dr, dc): Offset) -> T {
+        let (rows, cols) = self.cells.dim();
+        match self.edges {
+            Edges::Wrap => {
+                let row = (row as isize + dr).rem_euclid(rows as isize) as usize;
+                let col = (col as isize + dc).rem_euclid(cols as isize) as usize;
+                self.cells[(row, col)]
+            }
+            Edges::Background(background) => {
+                let row = row.checked_add_signed(dr).filter(|&r| r < rows);
+                let col = col.checked_add_signed(dc).filter(|&c| c < cols);
+                match (row, col) {
+                    (Some(row), Some(col)) => self.cells[(row, col)],
+                    _ => background,
+                }
+            }
+        }
+    }
+}
+
+impl<T> Space<T> for Dense<T>
+where
+    T: Copy + PartialEq + Hash + Send + Sync,
+{
+    fn apply(&mut self, rule: &Rule<T>) -> usize {
+        let mut next = self.cells.clone();
+        let changed = next
+            .axis_iter_mut(Axis(0))
+            .into_par_iter()
+            .enumerate()
+            .map(|(row, mut cells)| {
+                let mut neighbours = Vec::with_capacity(rule.offsets().len());
+                let mut changed = 0;
+                for (col, cell) in cells.iter_mut().enumerate() {
+                    neighbours.clear();
+                    let around = rule.offsets().iter();
+                    neighbours.extend(around.map(|&o| self.neighbour((row, col), o)));
+                    let value = rule.next(*cell, &neighbours);
+                    if value != *cell {
+                        *cell = value;
+                        changed += 1;
+                    }
+                }
+                changed
+            })
+            .sum();
+        self.cells = next;
+        changed
+    }
+
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.cells.hash(&mut hasher);
+        hasher.finish()
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+
+pub mod dense;
+pub mod sparse;
+
+// (row, column) from the cell being updated
+pub type Offset = (isize, isize);
+
+pub const MOORE: [Offset; 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+// the 3x3 square in reading order, the cell itself in the middle
+pub const SQUARE: [Offset; 9] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 0),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+type UpdateFn<T> = dyn Fn(T, &[T]) -> T + Send + Sync;
+
+// The next value of a cell from its current one and its neighbours', given
+// in the order of the offsets
+pub struct Rule<T> {
+    offsets: Vec<Offset>,
+    update: Box<UpdateFn<T>>,
+}
+
+pub enum Phase<T> {
+    Once(Rule<T>),
+    // applied again and again within the step until it changes nothing, it
+    // can't change infinitely many cells as there'd be no end to it
+    UntilStable(Rule<T>),
+}
+
+// A set of cells that every cell of can be updated at once from a rule
+pub trait Space<T> {
+    // returns how many cells changed, usize::MAX for infinitely many
+    fn apply(&mut self, rule: &Rule<T>) -> usize;
+    // equal for equal states, and most likely different for different ones
+    fn fingerprint(&self) -> u64;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepStats {
+    pub step: usize,
+    // cell updates that changed a value, over all the phases, usize::MAX
+    // for infinitely many
+    pub changed: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Settled {
+    // the first step that changed nothing
+    Steady { step: usize },
+    // the state after step `start + period` is the one after `start`
+    Cycle { start: usize, period: usize },
+}
+
+// Every step runs the phases in order over the whole space
+pub struct Automaton<T, S> {
+    pub space: S,
+    phases: Vec<Phase<T>>,
+    pub history: Vec<StepStats>,
+}
+
+impl<T> Rule<T> {
+    pub fn new(offsets: &[Offset], update: impl Fn(T, &[T]) -> T + Send + Sync + 'static) -> Self {
+        Rule {
+            offsets: offsets.to_vec(),
+            update: Box::new(update),
+        }
+    }
+
+    // A rule that only looks at the cell itself
+    pub fn map(update: impl Fn(T) -> T + Send + Sync + 'static) -> Self {
+        Rule::new(&[], move |cell, _| update(cell))
+    }
+
+    pub fn offsets(&self) -> &[Offset] {
+        &self.offsets
+    }
+
+    pub fn next(&self, cell: T, neighbours: &[T]) -> T {
+        (self.update)(cell, neighbours)
+    }
+}
+
+impl<T, S: Space<T>> Automaton<T, S> {
+    pub fn new(space: S, phases: Vec<Phase<T>>) -> Self {
+        Automaton {
+            space,
+            phases,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn steps(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn step(&mut self) -> Result<StepStats> {
+        let changed = Self::apply_phases(&mut self.space, &self.phases)?;
+        let stats = StepStats {
+            step: self.steps() + 1,
+            changed,
+        };
+        self.history.push(stats);
+        Ok(stats)
+    }
+
+    fn apply_phases(space: &mut S, phases: &[Phase<T>]) -> Result<usize> {
+        let mut changed = 0;
+        for phase in phases {
+            match phase {
+                Phase::Once(rule) => changed = space.apply(rule).saturating_add(changed),
+                Phase::UntilStable(rule) => loop {
+                    match space.apply(rule) {
+                        0 => break,
+                        usize::MAX => {
+                            bail!("A phase run until stable changed infinitely many cells")
+                        }
+                        n => changed = n.saturating_add(changed),
+                    }
+                },
+            }
+        }
+        Ok(changed)
+    }
+
+    pub fn run(&mut self, steps: usize) -> Result<&[StepStats]> {
+        let start = self.history.len();
+        for _ in 0..steps {
+            self.step()?;
+        }
+        Ok(&self.history[start..])
+    }
+
+    // Steps until nothing changes or a state comes back, None if that
+    // doesn't happen within `limit` steps
+    pub fn run_until_settled(&mut self, limit: usize) -> Result<Option<Settled>>
+    where
+        S: Clone + PartialEq,
+    {
+        // Only the fingerprints of the states are kept. When one comes back
+        // the earlier state is rebuilt from the first one and compared in
+        // full, in case they just collide.
+        let (first, initial) = (self.steps(), self.space.clone());
+        let mut seen: HashMap<u64, Vec<usize>> =
+            HashMap::from([(self.space.fingerprint(), vec![first])]);
+        for _ in 0..limit {
+            let stats = self.step()?;
+            if stats.changed == 0 {
+                return Ok(Some(Settled::Steady { step: stats.step }));
+            }
+            let steps = seen.entry(self.space.fingerprint()).or_default();
+            for &start in steps.iter() {
+                let mut state = initial.clone();
+                for _ in first..start {
+                    Self::apply_phases(&mut state, &self.phases)?;
+                }
+                if state == self.space {
+                    return Ok(Some(Settled::Cycle {
+                        start,
+                        period: stats.step - start,
+                    }));
+                }
+            }
+            steps.push(stats.step);
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ndarray::{Array2, array, s};
+
+    use super::{dense::*, sparse::*, *};
+
+    fn life() -> Vec<Phase<bool>> {
+        let rule = Rule::new(&MOORE, |alive, neighbours| {
+            let count = neighbours.iter().filter(|&&n| n).count();
+            count == 3 || (alive && count == 2)
+        });
+        vec![Phase::Once(rule)]
+    }
+
+    #[test]
+    fn settles() {
+        // a blinker flips between a row and a column
+        let cells = array![
+            [false, false, false],
+            [true, true, true],
+            [false, false, false]
+        ];
+        let space = Dense::new(cells.clone(), Edges::Background(false));
+        let mut blinker = Automaton::new(space, life());
+        let settled = blinker.run_until_settled(10).unwrap();
+        assert_eq!(
+            settled,
+            Some(Settled::Cycle {
+                start: 0,
+                period: 2
+            })
+        );
+        assert_eq!(
+            blinker.history[0],
+            StepStats {
+                step: 1,
+                changed: 4
+            }
+        );
+        assert_eq!(blinker.space.cells(), &cells);
+
+        // a block never changes
+        let block = Sparse::from_dense(&array![[true, true], [true, true]], false);
+        let mut block = Automaton::new(block, life());
+        assert_eq!(
+            block.run_until_settled(10).unwrap(),
+            Some(Settled::Steady { step: 1 })
+        );
+        assert_eq!(block.space.population(), 4);
+
+        // an empty grid that lights up and goes dark again every other step
+        let blink = Rule::new(&MOORE, |alive: bool, neighbours| {
+            !alive && neighbours.iter().all(|&n| !n)
+        });
+        let mut blink = Automaton::new(Sparse::new(false), vec![Phase::Once(blink)]);
+        assert_eq!(
+            blink.run_until_settled(10).unwrap(),
+            Some(Settled::Cycle {
+                start: 0,
+                period: 2
+            })
+        );
+        assert_eq!(blink.history[0].changed, usize::MAX);
+        assert_eq!(blink.space.population(), 0);
+        assert!(!blink.space.background());
+
+        // a glider keeps going on an infinite grid
+        let mut glider = Automaton::new(Sparse::from_dense(&glider_cells(), false), life());
+        assert_eq!(glider.run_until_settled(20).unwrap(), None);
+        assert_eq!(glider.steps(), 20);
+        assert!(glider.history.iter().all(|s| s.changed > 0));
+        assert_eq!(glider.space.population(), 5);
+        // but comes back round on a torus, moving a cell every 4 steps
+        let mut torus = Array2::from_elem((5, 5), false);
+        torus.slice_mut(s![..3, ..3]).assign(&glider_cells());
+        let mut glider = Automaton::new(Dense::new(torus, Edges::Wrap), life());
+        assert_eq!(
+            glider.run_until_settled(100).unwrap(),
+            Some(Settled::Cycle {
+                start: 0,
+                period: 20
+            })
+        );
+    }
+
+    // a single cell, with the worst fingerprint there is
+    #[derive(Clone, PartialEq)]
+    struct Cell(u8);
+
+    impl Space<u8> for Cell {
+        fn apply(&mut self, rule: &Rule<u8>) -> usize {
+            let next = rule.next(self.0, &[]);
+            (std::mem::replace(&mut self.0, next) != next) as usize
+        }
+
+        fn fingerprint(&self) -> u64 {
+            0
+        }
+    }
+
+    #[test]
+    fn fingerprint_collisions() {
+        let phases = vec![Phase::Once(Rule::map(|v| (v + 1) % 3))];
+        let mut counter = Automaton::new(Cell(0), phases);
+        assert_eq!(
+            counter.run_until_settled(10).unwrap(),
+            Some(Settled::Cycle {
+                start: 0,
+                period: 3
+            })
+        );
+    }
+
+    fn glider_cells() -> Array2<bool> {
+        array![
+            [false, true, false],
+            [false, false, true],
+            [true, true, true]
+        ]
+    }
+
+    #[test]
+    fn phases() {
+        // count up, then carry anything over 9 to the right neighbour
+        let phases = vec![
+            Phase::Once(Rule::map(|v: u8| v + 1)),
+            Phase::UntilStable(Rule::new(&[(0, -1)], |v, left| {
+                v % 10 + (left[0] >= 10) as u8
+            })),
+        ];
+        let space = Dense::new(array![[0, 8, 9]], Edges::Background(0));
+        let mut counter = Automaton::new(space, phases);
+        assert_eq!(counter.step().unwrap().changed, 4);
+        assert_eq!(counter.space.cells(), array![[1, 9, 0]]);
+        counter.step().unwrap();
+        assert_eq!(counter.space.cells(), array![[2, 0, 2]]);
+
+        // an empty grid flipping between dark and lit never gets stable
+        let flip = Phase::UntilStable(Rule::map(|lit: bool| !lit));
+        let mut flipper = Automaton::new(Sparse::new(false), vec![flip]);
+        assert!(flipper.step().is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use ndarray::Array2;
+
+use super::{Rule, Space};
+
+pub type Position = (i64, i64);
+
+// An unbounded grid where every cell not stored has the background value,
+// which the rules update like any other cell
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sparse<T> {
+    cells: HashMap<Position, T>,
+    background: T,
+}
+
+impl<T: Copy + PartialEq> Sparse<T> {
+    pub fn new(background: T) -> Self {
+        Sparse {
+            cells: HashMap::new(),
+            background,
+        }
+    }
+
+    // The grid with its top left cell at (0, 0)
+    pub fn from_dense(cells: &Array2<T>, background: T) -> Self {
+        let mut sparse = Sparse::new(background);
+        for ((row, col), &cell) in cells.indexed_iter() {
+            sparse.set((row as i64, col as i64), cell);
+        }
+        sparse
+    }
+
+    pub fn get(&self, position: Position) -> T {
+        self.cells
+            .get(&position)
+            .copied()
+            .unwrap_or(self.background)
+    }
+
+    pub fn set(&mut self, position: Position, cell: T) {
+        match cell == self.background {
+            true => self.cells.remove(&position),
+            false => self.cells.insert(position, cell),
+        };
+    }
+
+    #[cfg(test)]
+    pub fn background(&self) -> T {
+        self.background
+    }
+
+    // How many cells differ from the background
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+fn hash_of(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<T> Space<T> for Sparse<T>
+where
+    T: Copy + PartialEq + Hash,
+{
+    // Only cells near stored ones can end up different from the background.
+    // When the background itself changes, so do infinitely many cells.
+    fn apply(&mut self, rule: &Rule<T>) -> usize {
+        let offsets = rule.offsets();
+        let background = rule.next(self.background, &vec![self.background; offsets.len()]);
+        let candidates: HashSet<_> = self
+            .cells
+            .keys()
+            .flat_map(|&(row, col)| {
+                let affected = offsets
+                    .iter()
+                    .map(move |&(dr, dc)| (row - dr as i64, col - dc as i64));
+                affected.chain([(row, col)])
+            })
+            .collect();
+
+        let mut cells = HashMap::new();
+        let mut neighbours = Vec::with_capacity(offsets.len());
+        let mut changed = 0;
+        for (row, col) in candidates {
+            neighbours.clear();
+            let around = offsets.iter();
+            neighbours.extend(around.map(|&(dr, dc)| self.get((row + dr as i64, col + dc as i64))));
+            let old = self.get((row, col));
+            let value = rule.next(old, &neighbours);
+            if value != old {
+                changed += 1;
+            }
+            if value != background {
+                cells.insert((row, col), value);
+            }
+        }
+        let flipped = background != self.background;
+        self.cells = cells;
+        self.background = background;
+        match flipped {
+            true => usize::MAX,
+            false => changed,
+        }
+    }
+
+    fn fingerprint(&self) -> u64 {
+        // the map has no order, so the cells are combined in a way that has none
+        let cells = self.cells.iter().map(hash_of).fold(0, u64::wrapping_add);
+        cells ^ hash_of(self.background)
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, ensure};
+use nom::{
+    IResult,
+    character::complete::{space0, space1},
+    multi::separated_list1,
+    sequence::preceded,
+};
+
+use crate::parse::{lines, parse_unsigned};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinRule {
+    Rows,
+    Columns,
+    // both corner to corner ones, square boards only
+    Diagonals,
+    FullCard,
+}
+
+// Numbers in reading order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    numbers: Vec<u32>,
+    rows: usize,
+    columns: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Win {
+    pub board: usize,
+    // index of the draw that completed a line
+    pub draw: usize,
+    pub number: u32,
+    pub unmarked_sum: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Game {
+    boards: Vec<Board>,
+    // by board, the cells of every line that wins
+    lines: Vec<Vec<Vec<usize>>>,
+    // by board and cell, the lines through it
+    lines_through: Vec<Vec<Vec<usize>>>,
+    // every place a number is on, in board order
+    places: HashMap<u32, Vec<(usize, usize)>>,
+}
+
+impl Board {
+    pub fn new(rows: Vec<Vec<u32>>) -> Result<Self> {
+        let columns = rows.first().map_or(0, Vec::len);
+        ensure!(columns > 0, "The board is empty");
+        ensure!(
+            rows.iter().all(|row| row.len() == columns),
+            "The board's rows aren't all {} long",
+            columns
+        );
+        Ok(Board {
+            rows: rows.len(),
+            columns,
+            numbers: rows.concat(),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn numbers(&self) -> &[u32] {
+        &self.numbers
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.columns)
+    }
+
+    fn lines(&self, rule: WinRule) -> Vec<Vec<usize>> {
+        let (rows, columns) = (self.rows, self.columns);
+        match rule {
+            WinRule::Rows => (0..rows)
+                .map(|r| (0..columns).map(|c| r * columns + c).collect())
+                .collect(),
+            WinRule::Columns => (0..columns)
+                .map(|c| (0..rows).map(|r| r * columns + c).collect())
+                .collect(),
+            WinRule::Diagonals => vec![
+                (0..rows).map(|i| i * columns + i).collect(),
+                (0..rows).map(|i| i * columns + columns - 1 - i).collect(),
+            ],
+            WinRule::FullCard => vec![(0..rows * columns).collect()],
+        }
+    }
+}
+
+// Rows of numbers, any amount of spaces apart and before
+pub fn parse_board(input: &[u8]) -> IResult<&[u8], Vec<Vec<u32>>> {
+    lines(preceded(space0, separated_list1(space1, parse_unsigned)))(input)
+}
+
+impl Game {
+    pub fn new(boards: Vec<Board>, rules: &[WinRule]) -> Result<Self> {
+        ensure!(!rules.is_empty(), "No way to win");
+        let mut lines = Vec::new();
+        let mut lines_through = Vec::new();
+        let mut places: HashMap<_, Vec<_>> = HashMap::new();
+        for (i, board) in boards.iter().enumerate() {
+            if rules.contains(&WinRule::Diagonals) {
+                ensure!(
+                    board.rows == board.columns,
+                    "Board {} isn't square, it has no diagonals",
+                    i
+                );
+            }
+            let board_lines: Vec<_> = rules.iter().flat_map(|&rule| board.lines(rule)).collect();
+            let mut through = vec![Vec::new(); board.numbers.len()];
+            for (line, cells) in board_lines.iter().enumerate() {
+                for &cell in cells {
+                    through[cell].push(line);
+                }
+            }
+            for (cell, &number) in board.numbers.iter().enumerate() {
+                places.entry(number).or_default().push((i, cell));
+            }
+            lines.push(board_lines);
+            lines_through.push(through);
+        }
+        Ok(Game {
+            boards,
+            lines,
+            lines_through,
+            places,
+        })
+    }
+
+    pub fn boards(&self) -> &[Board] {
+        &self.boards
+    }
+
+    // Every board that wins, in the order they do. Boards that win on the
+    // same draw come in board order, and stop playing once they've won.
+    pub fn play(&self, draws: &[u32]) -> Vec<Win> {
+        let mut marked: Vec<_> = self
+            .boards
+            .iter()
+            .map(|b| vec![false; b.numbers.len()])
+            .collect();
+        let mut counts: Vec<_> = self.lines.iter().map(|l| vec![0; l.len()]).collect();
+        let mut won = vec![false; self.boards.len()];
+        let mut wins = Vec::new();
+        for (draw, &number) in draws.iter().enumerate() {
+            let places = self.places.get(&number).map_or(&[][..], Vec::as_slice);
+            // a board can have the number more than once, mark every copy
+            // before looking at what it won
+            for copies in places.chunk_by(|a, b| a.0 == b.0) {
+                let board = copies[0].0;
+                if won[board] {
+                    continue;
+                }
+                for &(_, cell) in copies {
+                    if marked[board][cell] {
+                        continue;
+                    }
+                    marked[board][cell] = true;
+                    for &line in &self.lines_through[board][cell] {
+                        counts[board][line] += 1;
+                        won[board] |= counts[board][line] == self.lines[board][line].len();
+                    }
+                }
+                if won[board] {
+                    let numbers = &self.boards[board].numbers;
+                    let unmarked = numbers.iter().zip(&marked[board]).filter(|(_, m)| !**m);
+                    wins.push(Win {
+                        board,
+                        draw,
+                        number,
+                        unmarked_sum: unmarked.map(|(n, _)| n).sum(),
+                    });
+                }
+            }
+        }
+        wins
+    }
+}
+
+impl Win {
+    pub fn score(&self) -> u32 {
+        self.unmarked_sum * self.number
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn board(rows: &[&[u32]]) -> Board {
+        Board::new(rows.iter().map(|r| r.to_vec()).collect()).unwrap()
+    }
+
+    #[test]
+    fn win_rules() {
+        let square = board(&[&[1, 2, 3], &[4, 5, 6], &[7, 8, 9]]);
+        let draws = [1, 5, 3, 7, 9, 2, 4, 6, 8];
+        let first_win = |rules: &[WinRule]| {
+            let game = Game::new(vec![square.clone()], rules).unwrap();
+            let wins = game.play(&draws);
+            assert!(wins.len() <= 1);
+            wins.first().map(|w| w.draw)
+        };
+        assert_eq!(first_win(&[WinRule::Diagonals]), Some(3));
+        assert_eq!(first_win(&[WinRule::Rows]), Some(5));
+        assert_eq!(first_win(&[WinRule::Columns]), Some(6));
+        assert_eq!(first_win(&[WinRule::FullCard]), Some(8));
+        assert_eq!(first_win(&[WinRule::Rows, WinRule::Diagonals]), Some(3));
+
+        let tall = board(&[&[1, 2], &[3, 4], &[5, 6]]);
+        assert!(Game::new(vec![tall.clone()], &[WinRule::Diagonals]).is_err());
+        let game = Game::new(vec![tall, square], &[WinRule::Columns]).unwrap();
+        // 1 and 3 are on both, draws not on any board are fine
+        let wins = game.play(&[100, 1, 3, 5, 7, 4, 6]);
+        assert_eq!(
+            wins,
+            [
+                Win {
+                    board: 0,
+                    draw: 3,
+                    number: 5,
+                    unmarked_sum: 12
+                },
+                Win {
+                    board: 1,
+                    draw: 5,
+                    number: 4,
+                    unmarked_sum: 2 + 6 + 8 + 9
+                }
+            ]
+        );
+        assert_eq!(wins[0].score(), 60);
+    }
+
+    #[test]
+    fn repeated_numbers() {
+        // the 2 completing the top row also marks the one below it
+        let game = Game::new(vec![board(&[&[1, 2], &[3, 2]])], &[WinRule::Rows]).unwrap();
+        let wins = game.play(&[1, 2]);
+        assert_eq!(
+            wins,
+            [Win {
+                board: 0,
+                draw: 1,
+                number: 2,
+                unmarked_sum: 3
+            }]
+        );
+        let game = Game::new(vec![board(&[&[5, 5], &[6, 7]])], &[WinRule::FullCard]).unwrap();
+        assert_eq!(game.play(&[5, 6, 7])[0].draw, 2);
+    }
+
+    #[test]
+    fn boards() {
+        let (_, rows) = parse_board(b" 1  2 30\n40  5  6").unwrap();
+        assert_eq!(rows, [[1, 2, 30], [40, 5, 6]]);
+        assert_eq!(Board::new(rows).unwrap().shape(), (2, 3));
+        assert!(Board::new(vec![vec![1, 2], vec![3]]).is_err());
+        assert!(Board::new(vec![]).is_err());
+    }
+}
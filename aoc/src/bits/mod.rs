@@ -0,0 +1,260 @@
+use std::fmt::Display;
+
+pub mod reader;
+#[cfg(test)]
+pub mod writer;
+
+pub const LITERAL_TYPE: u8 = 4;
+const COUNT_BITS: usize = 11;
+const LENGTH_BITS: usize = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Sum,
+    Product,
+    Minimum,
+    Maximum,
+    Greater,
+    Less,
+    Equal,
+}
+
+// How an operator packet says where its sub-packets end
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthType {
+    Bits,
+    Count,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Content {
+    Literal(u64),
+    Operator {
+        operator: Operator,
+        length_type: LengthType,
+        packets: Vec<Packet>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Packet {
+    pub version: u8,
+    pub content: Content,
+}
+
+impl Operator {
+    pub fn from_type_id(type_id: u8) -> Option<Self> {
+        use Operator::*;
+        Some(match type_id {
+            0 => Sum,
+            1 => Product,
+            2 => Minimum,
+            3 => Maximum,
+            5 => Greater,
+            6 => Less,
+            7 => Equal,
+            _ => return None,
+        })
+    }
+
+    pub fn type_id(self) -> u8 {
+        use Operator::*;
+        match self {
+            Sum => 0,
+            Product => 1,
+            Minimum => 2,
+            Maximum => 3,
+            Greater => 5,
+            Less => 6,
+            Equal => 7,
+        }
+    }
+
+    // How many sub-packets the operator can be applied to
+    pub fn accepts(self, count: usize) -> bool {
+        use Operator::*;
+        match self {
+            Sum | Product | Minimum | Maximum => count > 0,
+            Greater | Less | Equal => count == 2,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        use Operator::*;
+        match self {
+            Sum => "+",
+            Product => "*",
+            Minimum => "min",
+            Maximum => "max",
+            Greater => ">",
+            Less => "<",
+            Equal => "=",
+        }
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = format!("{:?}", self).to_lowercase();
+        write!(f, "{}", name)
+    }
+}
+
+impl Packet {
+    pub fn literal(version: u8, value: u64) -> Self {
+        Packet {
+            version,
+            content: Content::Literal(value),
+        }
+    }
+
+    pub fn operator(
+        version: u8,
+        operator: Operator,
+        length_type: LengthType,
+        packets: Vec<Packet>,
+    ) -> Self {
+        Packet {
+            version,
+            content: Content::Operator {
+                operator,
+                length_type,
+                packets,
+            },
+        }
+    }
+
+    pub fn sub_packets(&self) -> &[Packet] {
+        match &self.content {
+            Content::Literal(_) => &[],
+            Content::Operator { packets, .. } => packets,
+        }
+    }
+
+    pub fn version_sum(&self) -> u32 {
+        let inner: u32 = self.sub_packets().iter().map(Packet::version_sum).sum();
+        self.version as u32 + inner
+    }
+
+    // None if the packet doesn't make sense as an expression or overflows
+    pub fn evaluate(&self) -> Option<u64> {
+        use Operator::*;
+        let (operator, packets) = match &self.content {
+            Content::Literal(value) => return Some(*value),
+            Content::Operator {
+                operator, packets, ..
+            } => (*operator, packets),
+        };
+        if !operator.accepts(packets.len()) {
+            return None;
+        }
+        let mut values = packets.iter().map(Packet::evaluate);
+        Some(match operator {
+            Sum => values.try_fold(0u64, |acc, v| acc.checked_add(v?))?,
+            Product => values.try_fold(1u64, |acc, v| acc.checked_mul(v?))?,
+            Minimum => values.collect::<Option<Vec<_>>>()?.into_iter().min()?,
+            Maximum => values.collect::<Option<Vec<_>>>()?.into_iter().max()?,
+            Greater | Less | Equal => {
+                let (left, right) = (values.next()??, values.next()??);
+                match operator {
+                    Greater => (left > right) as u64,
+                    Less => (left < right) as u64,
+                    _ => (left == right) as u64,
+                }
+            }
+        })
+    }
+
+    // The expression as an S-expression, like "(+ 1 (* 2 3))"
+    pub fn to_sexpr(&self) -> String {
+        match &self.content {
+            Content::Literal(value) => value.to_string(),
+            Content::Operator {
+                operator, packets, ..
+            } => {
+                let mut sexpr = format!("({}", operator.symbol());
+                for packet in packets {
+                    sexpr.push(' ');
+                    sexpr.push_str(&packet.to_sexpr());
+                }
+                sexpr.push(')');
+                sexpr
+            }
+        }
+    }
+
+    fn write_tree(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
+        write!(f, "{:indent$}v{} ", "", self.version, indent = indent)?;
+        match &self.content {
+            Content::Literal(value) => writeln!(f, "type {} literal {}", LITERAL_TYPE, value),
+            Content::Operator {
+                operator,
+                length_type,
+                packets,
+            } => {
+                let length = match length_type {
+                    LengthType::Bits => "bit length",
+                    LengthType::Count => "packet count",
+                };
+                writeln!(f, "type {} {} ({})", operator.type_id(), operator, length)?;
+                packets
+                    .iter()
+                    .try_for_each(|packet| packet.write_tree(f, indent + 2))
+            }
+        }
+    }
+}
+
+// One packet per line, sub-packets indented under their operator
+impl Display for Packet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_tree(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tree_and_sexpr() {
+        let packet = Packet::operator(
+            1,
+            Operator::Sum,
+            LengthType::Count,
+            vec![
+                Packet::literal(2, 1),
+                Packet::operator(
+                    3,
+                    Operator::Product,
+                    LengthType::Bits,
+                    vec![Packet::literal(4, 2), Packet::literal(5, 3)],
+                ),
+            ],
+        );
+        assert_eq!(
+            packet.to_string(),
+            concat!(
+                "v1 type 0 sum (packet count)\n",
+                "  v2 type 4 literal 1\n",
+                "  v3 type 1 product (bit length)\n",
+                "    v4 type 4 literal 2\n",
+                "    v5 type 4 literal 3\n",
+            )
+        );
+        assert_eq!(packet.to_sexpr(), "(+ 1 (* 2 3))");
+        assert_eq!(packet.evaluate(), Some(7));
+        assert_eq!(packet.version_sum(), 15);
+
+        let bad = Packet::operator(0, Operator::Less, LengthType::Count, vec![]);
+        assert_eq!(bad.evaluate(), None);
+        let huge = Packet::literal(0, u64::MAX);
+        let overflow = Packet::operator(
+            0,
+            Operator::Sum,
+            LengthType::Count,
+            vec![huge.clone(), huge],
+        );
+        assert_eq!(overflow.evaluate(), None);
+    }
+}
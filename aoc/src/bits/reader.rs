@@ -0,0 +1,277 @@
+use std::fmt::Display;
+
+use super::{COUNT_BITS, LENGTH_BITS, LITERAL_TYPE, LengthType, Operator, Packet};
+use crate::parse::ascii_digit_to_value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeErrorKind {
+    InvalidHex(u8),
+    UnexpectedEnd,
+    UnknownType(u8),
+    LiteralTooLarge,
+    // the sub-packets run past the bit length of their operator
+    LengthMismatch { expected: usize, actual: usize },
+    OperandCount { operator: Operator, count: usize },
+    TrailingData,
+}
+
+// What went wrong and at which bit of the transmission
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    pub offset: usize,
+    pub kind: DecodeErrorKind,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use DecodeErrorKind::*;
+        write!(f, "bit {}: ", self.offset)?;
+        match self.kind {
+            InvalidHex(ch) => write!(f, "{:?} is not a hex digit", ch as char),
+            UnexpectedEnd => write!(f, "the transmission ends in the middle of a packet"),
+            UnknownType(id) => write!(f, "unknown packet type {}", id),
+            LiteralTooLarge => write!(f, "literal doesn't fit in 64 bits"),
+            LengthMismatch { expected, actual } => write!(
+                f,
+                "sub-packets take {} bits instead of {}",
+                actual, expected
+            ),
+            OperandCount { operator, count } => {
+                write!(f, "{} can't take {} sub-packets", operator, count)
+            }
+            TrailingData => write!(f, "non-zero bits after the packet"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// Reads bits straight out of hex text, most significant first
+pub struct BitReader<'a> {
+    hex: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(hex: &'a [u8]) -> Self {
+        BitReader { hex, offset: 0 }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.hex.len() * 4 - self.offset
+    }
+
+    fn error(&self, kind: DecodeErrorKind) -> DecodeError {
+        DecodeError {
+            offset: self.offset,
+            kind,
+        }
+    }
+
+    pub fn read_bit(&mut self) -> Result<bool, DecodeError> {
+        let ch = *self
+            .hex
+            .get(self.offset / 4)
+            .ok_or_else(|| self.error(DecodeErrorKind::UnexpectedEnd))?;
+        let digit = ascii_digit_to_value(ch)
+            .filter(|&d| d < 16)
+            .ok_or_else(|| self.error(DecodeErrorKind::InvalidHex(ch)))?;
+        let bit = (digit >> (3 - self.offset % 4)) & 1 == 1;
+        self.offset += 1;
+        Ok(bit)
+    }
+
+    // Up to 64 bits as a number
+    pub fn read(&mut self, bits: usize) -> Result<u64, DecodeError> {
+        debug_assert!(bits <= 64);
+        (0..bits).try_fold(0, |value, _| Ok((value << 1) | self.read_bit()? as u64))
+    }
+}
+
+fn decode_literal(reader: &mut BitReader) -> Result<u64, DecodeError> {
+    let start = reader.offset();
+    let mut value: u64 = 0;
+    loop {
+        let more = reader.read_bit()?;
+        if value.leading_zeros() < 4 {
+            return Err(DecodeError {
+                offset: start,
+                kind: DecodeErrorKind::LiteralTooLarge,
+            });
+        }
+        value = (value << 4) | reader.read(4)?;
+        if !more {
+            return Ok(value);
+        }
+    }
+}
+
+pub fn decode_packet(reader: &mut BitReader) -> Result<Packet, DecodeError> {
+    let start = reader.offset();
+    let version = reader.read(3)? as u8;
+    let type_id = reader.read(3)? as u8;
+    if type_id == LITERAL_TYPE {
+        let value = decode_literal(reader)?;
+        return Ok(Packet::literal(version, value));
+    }
+    let operator = Operator::from_type_id(type_id).ok_or(DecodeError {
+        offset: start + 3,
+        kind: DecodeErrorKind::UnknownType(type_id),
+    })?;
+
+    let mut packets = Vec::new();
+    let length_type = match reader.read_bit()? {
+        true => {
+            let count = reader.read(COUNT_BITS)? as usize;
+            for _ in 0..count {
+                packets.push(decode_packet(reader)?);
+            }
+            LengthType::Count
+        }
+        false => {
+            let expected = reader.read(LENGTH_BITS)? as usize;
+            let first = reader.offset();
+            while reader.offset() - first < expected {
+                packets.push(decode_packet(reader)?);
+            }
+            let actual = reader.offset() - first;
+            if actual != expected {
+                return Err(DecodeError {
+                    offset: first,
+                    kind: DecodeErrorKind::LengthMismatch { expected, actual },
+                });
+            }
+            LengthType::Bits
+        }
+    };
+    if !operator.accepts(packets.len()) {
+        return Err(DecodeError {
+            offset: start,
+            kind: DecodeErrorKind::OperandCount {
+                operator,
+                count: packets.len(),
+            },
+        });
+    }
+    Ok(Packet::operator(version, operator, length_type, packets))
+}
+
+// Decodes a whole transmission: one packet, then only zero padding
+pub fn decode(hex: &[u8]) -> Result<Packet, DecodeError> {
+    let hex = hex.trim_ascii_end();
+    let mut reader = BitReader::new(hex);
+    let packet = decode_packet(&mut reader)?;
+    let end = reader.offset();
+    while reader.remaining() > 0 {
+        if reader.read_bit()? {
+            return Err(DecodeError {
+                offset: end,
+                kind: DecodeErrorKind::TrailingData,
+            });
+        }
+    }
+    Ok(packet)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bits::writer::{BitWriter, encode};
+    use DecodeErrorKind::*;
+
+    #[test]
+    fn puzzle_examples() {
+        assert_eq!(decode(b"D2FE28\n"), Ok(Packet::literal(6, 2021)));
+        let packet = decode(b"38006F45291200").unwrap();
+        assert_eq!(packet.to_sexpr(), "(< 10 20)");
+        assert_eq!(decode(b"EE00D40C823060").unwrap().to_sexpr(), "(max 1 2 3)");
+        let versions = [
+            ("8A004A801A8002F478", 16),
+            ("620080001611562C8802118E34", 12),
+            ("C0015000016115A2E0802F182340", 23),
+            ("A0016C880162017C3686B18A3D4780", 31),
+        ];
+        for (hex, sum) in versions {
+            assert_eq!(
+                decode(hex.as_bytes()).unwrap().version_sum(),
+                sum,
+                "{}",
+                hex
+            );
+        }
+        let values = [
+            ("C200B40A82", 3),
+            ("04005AC33890", 54),
+            ("880086C3E88112", 7),
+            ("CE00C43D881120", 9),
+            ("D8005AC2A8F0", 1),
+            ("F600BC2D8F", 0),
+            ("9C005AC2F8F0", 0),
+            ("9C0141080250320F1802104A08", 1),
+        ];
+        for (hex, value) in values {
+            let packet = decode(hex.as_bytes()).unwrap();
+            assert_eq!(packet.evaluate(), Some(value), "{}", hex);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let error = |hex: &str| decode(hex.as_bytes()).unwrap_err();
+        assert_eq!(error("D2FG28").kind, InvalidHex(b'G'));
+        assert_eq!(error("D2FG28").offset, 12);
+        assert_eq!(error("D2FE").kind, UnexpectedEnd);
+        assert_eq!(error("D2FE").offset, 16);
+        assert_eq!(error("D2FE29").kind, TrailingData);
+        assert_eq!(error("D2FE29").offset, 21);
+        assert_eq!(
+            error("D2FG28").to_string(),
+            "bit 12: 'G' is not a hex digit"
+        );
+
+        let literals = (1..=3).map(|v| Packet::literal(0, v)).collect();
+        let less = Packet::operator(2, Operator::Less, LengthType::Count, literals);
+        let less = error(&encode(&less).unwrap());
+        assert_eq!(less.offset, 0);
+        assert_eq!(
+            less.kind,
+            OperandCount {
+                operator: Operator::Less,
+                count: 3
+            }
+        );
+
+        // a sum claiming 10 bits of sub-packets, followed by an 11 bit literal
+        let mut writer = BitWriter::default();
+        writer.write(1, 3);
+        writer.write(Operator::Sum.type_id() as u64, 3);
+        writer.write_bit(false);
+        writer.write(10, LENGTH_BITS);
+        writer.write(0, 3);
+        writer.write(LITERAL_TYPE as u64, 3);
+        writer.write(0b00001, 5);
+        let mismatch = error(&writer.to_hex());
+        assert_eq!(mismatch.offset, 22);
+        assert_eq!(
+            mismatch.kind,
+            LengthMismatch {
+                expected: 10,
+                actual: 11
+            }
+        );
+
+        let mut writer = BitWriter::default();
+        writer.write(0, 3);
+        writer.write(LITERAL_TYPE as u64, 3);
+        for _ in 0..16 {
+            writer.write(0b1_1111, 5);
+        }
+        writer.write(0b0_1111, 5);
+        assert_eq!(error(&writer.to_hex()).kind, LiteralTooLarge);
+        assert_eq!(error(&writer.to_hex()).offset, 6);
+    }
+}
@@ -0,0 +1,168 @@
+use anyhow::{Result, ensure};
+
+use super::{COUNT_BITS, Content, LENGTH_BITS, LITERAL_TYPE, LengthType, Packet};
+
+// Bits packed most significant first, the last byte only partly used
+#[derive(Clone, Debug, Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    pub fn write_bit(&mut self, bit: bool) {
+        if self.bits.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bits % 8);
+        }
+        self.bits += 1;
+    }
+
+    // The lowest `bits` bits of value
+    pub fn write(&mut self, value: u64, bits: usize) {
+        for i in (0..bits).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+    }
+
+    pub fn append(&mut self, other: &BitWriter) {
+        for i in 0..other.bits {
+            self.write_bit(other.bytes[i / 8] & (0x80 >> (i % 8)) != 0);
+        }
+    }
+
+    // Padded with zeros to whole bytes, like the transmissions are
+    pub fn to_hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+}
+
+pub fn encode_packet(packet: &Packet, writer: &mut BitWriter) -> Result<()> {
+    ensure!(
+        packet.version < 8,
+        "Version {} needs more than 3 bits",
+        packet.version
+    );
+    writer.write(packet.version as u64, 3);
+    match &packet.content {
+        Content::Literal(value) => {
+            writer.write(LITERAL_TYPE as u64, 3);
+            let groups = (u64::BITS - value.leading_zeros()).div_ceil(4).max(1);
+            for group in (0..groups).rev() {
+                writer.write_bit(group > 0);
+                writer.write(value >> (4 * group), 4);
+            }
+        }
+        Content::Operator {
+            operator,
+            length_type,
+            packets,
+        } => {
+            writer.write(operator.type_id() as u64, 3);
+            match length_type {
+                LengthType::Count => {
+                    ensure!(
+                        packets.len() < 1 << COUNT_BITS,
+                        "{} sub-packets don't fit in the count",
+                        packets.len()
+                    );
+                    writer.write_bit(true);
+                    writer.write(packets.len() as u64, COUNT_BITS);
+                    for inner in packets {
+                        encode_packet(inner, writer)?;
+                    }
+                }
+                LengthType::Bits => {
+                    let mut inner_writer = BitWriter::default();
+                    for inner in packets {
+                        encode_packet(inner, &mut inner_writer)?;
+                    }
+                    ensure!(
+                        inner_writer.bits() < 1 << LENGTH_BITS,
+                        "{} bits of sub-packets don't fit in the length",
+                        inner_writer.bits()
+                    );
+                    writer.write_bit(false);
+                    writer.write(inner_writer.bits() as u64, LENGTH_BITS);
+                    writer.append(&inner_writer);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// The packet as a hex transmission. Operands aren't checked, so packets
+// that won't decode can be made too.
+pub fn encode(packet: &Packet) -> Result<String> {
+    let mut writer = BitWriter::default();
+    encode_packet(packet, &mut writer)?;
+    Ok(writer.to_hex())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        bits::{Operator, reader::decode},
+        rng::Rng,
+    };
+
+    fn random_packet(rng: &mut Rng, depth: usize) -> Packet {
+        let version = rng.below(8) as u8;
+        if depth == 0 || rng.below(3) == 0 {
+            let bits = rng.below(65);
+            return Packet::literal(version, rng.below(u64::MAX) >> (64 - bits).min(63));
+        }
+        let operator = loop {
+            if let Some(operator) = Operator::from_type_id(rng.below(8) as u8) {
+                break operator;
+            }
+        };
+        let count = match operator.accepts(1) {
+            true => 1 + rng.below(4) as usize,
+            false => 2,
+        };
+        let length_type = match rng.below(2) {
+            0 => LengthType::Bits,
+            _ => LengthType::Count,
+        };
+        let packets = (0..count).map(|_| random_packet(rng, depth - 1)).collect();
+        Packet::operator(version, operator, length_type, packets)
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut rng = Rng::new(0x2021_1216);
+        for _ in 0..500 {
+            let packet = random_packet(&mut rng, 4);
+            let hex = encode(&packet).unwrap();
+            assert_eq!(decode(hex.as_bytes()), Ok(packet.clone()), "{}", hex);
+        }
+        for hex in [
+            "D2FE28",
+            "38006F45291200",
+            "EE00D40C823060",
+            "8A004A801A8002F478",
+        ] {
+            let packet = decode(hex.as_bytes()).unwrap();
+            assert_eq!(encode(&packet).unwrap(), hex);
+        }
+    }
+
+    #[test]
+    fn field_limits() {
+        assert!(encode(&Packet::literal(8, 1)).is_err());
+        let many = vec![Packet::literal(0, 0); 1 << 11];
+        let sum = Packet::operator(0, Operator::Sum, LengthType::Count, many.clone());
+        assert!(encode(&sum).is_err());
+        let sum = Packet::operator(0, Operator::Sum, LengthType::Bits, many);
+        assert!(encode(&sum).is_ok());
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{Result, ensure};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pair {
+    pub open: u8,
+    pub close: u8,
+    // what finding this closer where another one belongs costs
+    pub corruption_score: u64,
+    // what closing with it while autocompleting is worth
+    pub completion_score: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Delimiters {
+    pairs: Vec<Pair>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxError {
+    // a closer that doesn't match the innermost open chunk
+    Corrupted {
+        position: usize,
+        expected: u8,
+        found: u8,
+    },
+    // a closer with no chunk open at all
+    Unopened {
+        position: usize,
+        found: u8,
+    },
+    // neither an opener nor a closer
+    Unknown {
+        position: usize,
+        found: u8,
+    },
+}
+
+// Everything wrong with a line, and what closes the chunks it left open
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub errors: Vec<SyntaxError>,
+    pub completion: Vec<u8>,
+}
+
+impl Pair {
+    pub const fn new(open: u8, close: u8, corruption_score: u64, completion_score: u64) -> Self {
+        Pair {
+            open,
+            close,
+            corruption_score,
+            completion_score,
+        }
+    }
+}
+
+impl Delimiters {
+    pub fn new(pairs: &[Pair]) -> Result<Self> {
+        let mut used: Vec<_> = pairs.iter().flat_map(|p| [p.open, p.close]).collect();
+        used.sort_unstable();
+        let repeated = used.windows(2).find(|w| w[0] == w[1]);
+        ensure!(
+            repeated.is_none(),
+            "{:?} delimits more than one kind of chunk",
+            repeated.map(|w| w[0] as char).unwrap_or_default()
+        );
+        Ok(Delimiters {
+            pairs: pairs.to_vec(),
+        })
+    }
+
+    // The navigation subsystem's chunks
+    pub fn chunks() -> Self {
+        Delimiters::new(&[
+            Pair::new(b'(', b')', 3, 1),
+            Pair::new(b'[', b']', 57, 2),
+            Pair::new(b'{', b'}', 1197, 3),
+            Pair::new(b'<', b'>', 25137, 4),
+        ])
+        .unwrap()
+    }
+
+    fn opened_by(&self, open: u8) -> Option<&Pair> {
+        self.pairs.iter().find(|p| p.open == open)
+    }
+
+    fn closed_by(&self, close: u8) -> Option<&Pair> {
+        self.pairs.iter().find(|p| p.close == close)
+    }
+
+    // Goes through the whole line whatever's wrong with it. A wrong closer
+    // still closes the innermost chunk, stray characters are skipped.
+    pub fn check(&self, line: &[u8]) -> Report {
+        let mut open: Vec<&Pair> = Vec::new();
+        let mut errors = Vec::new();
+        for (position, &found) in line.iter().enumerate() {
+            if let Some(pair) = self.opened_by(found) {
+                open.push(pair);
+            } else if self.closed_by(found).is_none() {
+                errors.push(SyntaxError::Unknown { position, found });
+            } else {
+                match open.pop() {
+                    Some(pair) if pair.close == found => {}
+                    Some(pair) => errors.push(SyntaxError::Corrupted {
+                        position,
+                        expected: pair.close,
+                        found,
+                    }),
+                    None => errors.push(SyntaxError::Unopened { position, found }),
+                }
+            }
+        }
+        Report {
+            errors,
+            completion: open.iter().rev().map(|p| p.close).collect(),
+        }
+    }
+
+    pub fn corruption_score(&self, error: &SyntaxError) -> u64 {
+        match *error {
+            SyntaxError::Corrupted { found, .. } => {
+                self.closed_by(found).map_or(0, |p| p.corruption_score)
+            }
+            SyntaxError::Unopened { .. } | SyntaxError::Unknown { .. } => 0,
+        }
+    }
+
+    // None if it doesn't fit in a u64
+    pub fn completion_score(&self, completion: &[u8]) -> Option<u64> {
+        completion.iter().try_fold(0u64, |score, &close| {
+            let pair = self.closed_by(close);
+            score
+                .checked_mul(5)?
+                .checked_add(pair.map_or(0, |p| p.completion_score))
+        })
+    }
+}
+
+impl Report {
+    pub fn first_error(&self) -> Option<&SyntaxError> {
+        self.errors.first()
+    }
+
+    // Error free, though maybe still open
+    pub fn is_incomplete(&self) -> bool {
+        self.errors.is_empty() && !self.completion.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recovers() {
+        let chunks = Delimiters::chunks();
+        assert_eq!(
+            chunks.check(b"([)]>{"),
+            Report {
+                errors: vec![
+                    SyntaxError::Corrupted {
+                        position: 2,
+                        expected: b']',
+                        found: b')'
+                    },
+                    SyntaxError::Corrupted {
+                        position: 3,
+                        expected: b')',
+                        found: b']'
+                    },
+                    SyntaxError::Unopened {
+                        position: 4,
+                        found: b'>'
+                    },
+                ],
+                completion: b"}".to_vec(),
+            }
+        );
+        let leading = chunks.check(b")()");
+        assert_eq!(
+            leading.first_error(),
+            Some(&SyntaxError::Unopened {
+                position: 0,
+                found: b')'
+            })
+        );
+        assert!(!leading.is_incomplete());
+        assert_eq!(chunks.corruption_score(leading.first_error().unwrap()), 0);
+        assert!(chunks.check(b"<{}>").completion.is_empty());
+    }
+
+    #[test]
+    fn other_delimiters() {
+        let quotes =
+            Delimiters::new(&[Pair::new(b'/', b'\\', 10, 7), Pair::new(b'(', b')', 1, 1)]).unwrap();
+        let report = quotes.check(b"/(x)/(\\");
+        assert_eq!(
+            report.errors,
+            [
+                SyntaxError::Unknown {
+                    position: 2,
+                    found: b'x'
+                },
+                SyntaxError::Corrupted {
+                    position: 6,
+                    expected: b')',
+                    found: b'\\'
+                }
+            ]
+        );
+        assert_eq!(quotes.corruption_score(&report.errors[1]), 10);
+        assert_eq!(report.completion, b"\\\\");
+        assert_eq!(quotes.completion_score(b")\\\\"), Some(25 + 7 * 5 + 7));
+        assert_eq!(quotes.completion_score(&[b'\\'; 30]), None);
+        assert!(Delimiters::new(&[Pair::new(b'|', b'|', 1, 1)]).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use std::{cmp::Ordering, fmt};
+
+use anyhow::{Result, bail, ensure};
+
+// Which bit a criterion keeps when both are as common
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tie {
+    Zero,
+    One,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Criterion {
+    MostCommon,
+    LeastCommon,
+}
+
+// Bits most significant first
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bits(pub Vec<bool>);
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Node {
+    // lines going through the node
+    count: usize,
+    // by bit, 0 for none as the root is no one's child
+    children: [usize; 2],
+}
+
+// The report's lines in a binary trie, so filtering by a bit is following
+// an edge and every prefix knows how many lines share it
+#[derive(Clone, Debug)]
+pub struct Report {
+    width: usize,
+    nodes: Vec<Node>,
+    // by column, how many lines have a 1 there
+    ones: Vec<usize>,
+}
+
+impl Criterion {
+    fn pick(self, zeros: usize, ones: usize, tie: Tie) -> bool {
+        match (zeros.cmp(&ones), self) {
+            (Ordering::Equal, _) => tie == Tie::One,
+            (ordering, Criterion::MostCommon) => ordering.is_lt(),
+            (ordering, Criterion::LeastCommon) => ordering.is_gt(),
+        }
+    }
+}
+
+impl Bits {
+    // None if it takes more than 64 bits
+    pub fn value(&self) -> Option<u64> {
+        let leading = self.0.iter().take_while(|&&bit| !bit).count();
+        if self.0.len() - leading > u64::BITS as usize {
+            return None;
+        }
+        Some(self.0.iter().fold(0, |value, &bit| value << 1 | bit as u64))
+    }
+}
+
+impl fmt::Display for Bits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &bit in &self.0 {
+            write!(f, "{}", bit as u8)?;
+        }
+        Ok(())
+    }
+}
+
+impl Report {
+    pub fn new(lines: &[&[u8]]) -> Result<Self> {
+        let Some(first) = lines.first() else {
+            bail!("The report is empty");
+        };
+        let width = first.len();
+        ensure!(width > 0, "The report's lines are empty");
+        let mut report = Report {
+            width,
+            nodes: vec![Node::default()],
+            ones: vec![0; width],
+        };
+        for (number, line) in lines.iter().enumerate() {
+            ensure!(
+                line.len() == width,
+                "Line {} is {} bits wide, not {}",
+                number + 1,
+                line.len(),
+                width
+            );
+            let mut node = 0;
+            report.nodes[0].count += 1;
+            for (column, &digit) in line.iter().enumerate() {
+                let bit = match digit {
+                    b'0' => 0,
+                    b'1' => 1,
+                    _ => bail!("Line {} has a {:?}", number + 1, digit as char),
+                };
+                report.ones[column] += bit;
+                if report.nodes[node].children[bit] == 0 {
+                    report.nodes[node].children[bit] = report.nodes.len();
+                    report.nodes.push(Node::default());
+                }
+                node = report.nodes[node].children[bit];
+                report.nodes[node].count += 1;
+            }
+        }
+        Ok(report)
+    }
+
+    #[cfg(test)]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn lines(&self) -> usize {
+        self.nodes[0].count
+    }
+
+    // Lines starting with `prefix`
+    #[cfg(test)]
+    pub fn count(&self, prefix: &[bool]) -> usize {
+        let mut node = 0;
+        for &bit in prefix {
+            node = self.nodes[node].children[bit as usize];
+            if node == 0 {
+                return 0;
+            }
+        }
+        self.nodes[node].count
+    }
+
+    // The bit the criterion picks in every column over all the lines
+    pub fn columns(&self, criterion: Criterion, tie: Tie) -> Bits {
+        let bits = self.ones.iter().map(|&ones| {
+            let zeros = self.lines() - ones;
+            criterion.pick(zeros, ones, tie)
+        });
+        Bits(bits.collect())
+    }
+
+    pub fn gamma(&self) -> Bits {
+        self.columns(Criterion::MostCommon, Tie::One)
+    }
+
+    pub fn epsilon(&self) -> Bits {
+        self.columns(Criterion::LeastCommon, Tie::Zero)
+    }
+
+    // Keeps only the lines with the bit the criterion picks among those
+    // left, a column at a time, until one line is left
+    pub fn rating(&self, criterion: Criterion, tie: Tie) -> Bits {
+        let mut node = 0;
+        let mut bits = Vec::with_capacity(self.width);
+        for _ in 0..self.width {
+            let [zeros, ones] = self.nodes[node].children.map(|child| match child {
+                0 => 0,
+                child => self.nodes[child].count,
+            });
+            // with one line left there's only one way to go
+            let bit = match (zeros, ones) {
+                (0, _) => true,
+                (_, 0) => false,
+                _ => criterion.pick(zeros, ones, tie),
+            };
+            bits.push(bit);
+            node = self.nodes[node].children[bit as usize];
+        }
+        Bits(bits)
+    }
+
+    pub fn oxygen_rating(&self) -> Bits {
+        self.rating(Criterion::MostCommon, Tie::One)
+    }
+
+    pub fn co2_rating(&self) -> Bits {
+        self.rating(Criterion::LeastCommon, Tie::Zero)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn report(lines: &[&str]) -> Report {
+        let lines: Vec<_> = lines.iter().map(|l| l.as_bytes()).collect();
+        Report::new(&lines).unwrap()
+    }
+
+    #[test]
+    fn ties_and_widths() {
+        let even = report(&["01", "10", "11", "00"]);
+        assert_eq!(even.gamma().to_string(), "11");
+        assert_eq!(even.epsilon().to_string(), "00");
+        assert_eq!(
+            even.columns(Criterion::MostCommon, Tie::Zero).to_string(),
+            "00"
+        );
+        assert_eq!(even.oxygen_rating().to_string(), "11");
+        assert_eq!(even.co2_rating().to_string(), "00");
+        assert_eq!(
+            even.rating(Criterion::LeastCommon, Tie::One).to_string(),
+            "11"
+        );
+
+        // wider than any integer, with a line that's there twice
+        let wide = "1".repeat(100);
+        let narrow = format!("0{}", "1".repeat(99));
+        let wide = report(&[&wide, &narrow, &narrow]);
+        assert_eq!(wide.count(&[false]), 2);
+        assert_eq!(wide.oxygen_rating(), Bits(narrow_bits()));
+        assert_eq!(wide.co2_rating(), Bits(vec![true; 100]));
+        assert_eq!(wide.co2_rating().value(), None);
+        assert_eq!(Bits(narrow_bits()[36..].to_vec()).value(), Some(u64::MAX));
+
+        assert!(Report::new(&[]).is_err());
+        assert!(Report::new(&[&b"010"[..], b"01"]).is_err());
+        assert!(Report::new(&[&b"012"[..]]).is_err());
+    }
+
+    fn narrow_bits() -> Vec<bool> {
+        (0..100).map(|i| i > 0).collect()
+    }
+}
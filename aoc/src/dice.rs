@@ -0,0 +1,280 @@
+use std::cmp::Ordering;
+
+use anyhow::{Context, Result, ensure};
+
+// Players move round a circular board numbered from 1, by the sum of their
+// rolls, and score the number they land on. The first to `target` wins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rules {
+    pub board_size: u32,
+    pub die_faces: u32,
+    pub rolls_per_turn: u32,
+    pub target: u32,
+}
+
+// How a game with a deterministic die went
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub winner: usize,
+    pub scores: Vec<u32>,
+    pub rolls: u32,
+}
+
+impl Rules {
+    pub const DETERMINISTIC: Rules = Rules {
+        board_size: 10,
+        die_faces: 100,
+        rolls_per_turn: 3,
+        target: 1000,
+    };
+    pub const DIRAC: Rules = Rules {
+        board_size: 10,
+        die_faces: 3,
+        rolls_per_turn: 3,
+        target: 21,
+    };
+
+    fn check(&self, starts: &[u32]) -> Result<()> {
+        ensure!(
+            self.board_size > 0 && self.die_faces > 0 && self.rolls_per_turn > 0,
+            "The board, die and turns can't be empty"
+        );
+        ensure!(self.target > 0, "The target score has to be above 0");
+        ensure!(!starts.is_empty(), "There are no players");
+        for &start in starts {
+            ensure!(
+                (1..=self.board_size).contains(&start),
+                "Starting position {} isn't on the board",
+                start
+            );
+        }
+        Ok(())
+    }
+
+    // The ways every total of a turn's rolls can come up, by total
+    fn totals(&self) -> Vec<u128> {
+        let mut ways = vec![1];
+        for _ in 0..self.rolls_per_turn {
+            let mut next = vec![0; ways.len() + self.die_faces as usize];
+            for (total, &count) in ways.iter().enumerate() {
+                for face in 1..=self.die_faces as usize {
+                    next[total + face] += count;
+                }
+            }
+            ways = next;
+        }
+        ways
+    }
+
+    fn landing(&self, position: u32, total: u32) -> u32 {
+        (position - 1 + total % self.board_size) % self.board_size + 1
+    }
+}
+
+// Plays with a die that rolls 1, 2, 3 and so on, starting over after its
+// last face
+pub fn play_deterministic(rules: &Rules, starts: &[u32]) -> Result<Outcome> {
+    rules.check(starts)?;
+    let mut positions = starts.to_vec();
+    let mut scores = vec![0; starts.len()];
+    let mut rolls = 0;
+    for player in (0..starts.len()).cycle() {
+        let mut total = 0;
+        for _ in 0..rules.rolls_per_turn {
+            total += rolls % rules.die_faces + 1;
+            rolls += 1;
+        }
+        positions[player] = rules.landing(positions[player], total);
+        scores[player] += positions[player];
+        if scores[player] >= rules.target {
+            return Ok(Outcome {
+                winner: player,
+                scores,
+                rolls,
+            });
+        }
+    }
+    unreachable!()
+}
+
+// For one player on their own: the universes in which they reach the
+// target on their n-th turn, and those in which they still haven't after it
+struct Progress {
+    won_on: Vec<u128>,
+    playing_after: Vec<u128>,
+}
+
+impl Progress {
+    fn new(rules: &Rules, start: u32, totals: &[u128]) -> Result<Self> {
+        let (size, target) = (rules.board_size as usize, rules.target as usize);
+        // universes by position and score, positions from 0
+        let mut table = vec![0u128; size * target];
+        table[(start as usize - 1) * target] = 1;
+        let mut progress = Progress {
+            won_on: vec![0],
+            playing_after: vec![1],
+        };
+        while *progress.playing_after.last().unwrap() > 0 {
+            let mut next = vec![0u128; size * target];
+            let mut won = 0u128;
+            for (cell, &count) in table.iter().enumerate().filter(|(_, c)| **c > 0) {
+                let (position, score) = (cell / target, cell % target);
+                for (total, &ways) in totals.iter().enumerate().filter(|(_, w)| **w > 0) {
+                    let universes = count.checked_mul(ways).context("Universes overflow")?;
+                    let landed = (position + total) % size;
+                    let score = score + landed + 1;
+                    let slot = match score >= target {
+                        true => &mut won,
+                        false => &mut next[landed * target + score],
+                    };
+                    *slot = slot.checked_add(universes).context("Universes overflow")?;
+                }
+            }
+            let playing = next.iter().try_fold(0u128, |sum, &c| sum.checked_add(c));
+            progress.won_on.push(won);
+            progress
+                .playing_after
+                .push(playing.context("Universes overflow")?);
+            table = next;
+        }
+        Ok(progress)
+    }
+
+    fn playing_after(&self, turn: usize) -> u128 {
+        self.playing_after.get(turn).copied().unwrap_or(0)
+    }
+}
+
+// The universes each player wins in when every roll splits the universe
+// into one per face. Players only ever depend on each other through who
+// gets to the target first, so each one's progress is worked out alone
+// and then combined turn by turn.
+pub fn count_wins(rules: &Rules, starts: &[u32]) -> Result<Vec<u128>> {
+    rules.check(starts)?;
+    let totals = rules.totals();
+    let players = starts
+        .iter()
+        .map(|&start| Progress::new(rules, start, &totals))
+        .collect::<Result<Vec<_>>>()?;
+
+    let overflow = || "Universes overflow";
+    let mut wins = vec![0u128; players.len()];
+    for (player, progress) in players.iter().enumerate() {
+        for (turn, &won) in progress.won_on.iter().enumerate().skip(1) {
+            // those before have had this turn already, those after haven't
+            let mut universes = won;
+            for (other, others) in players.iter().enumerate() {
+                let turns = match other.cmp(&player) {
+                    Ordering::Less => turn,
+                    Ordering::Equal => continue,
+                    Ordering::Greater => turn - 1,
+                };
+                universes = universes
+                    .checked_mul(others.playing_after(turns))
+                    .with_context(overflow)?;
+            }
+            wins[player] = wins[player].checked_add(universes).with_context(overflow)?;
+        }
+    }
+    Ok(wins)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    // every universe followed through the whole game state
+    fn brute_force(rules: &Rules, starts: &[u32]) -> Vec<u128> {
+        type State = (usize, Vec<u32>, Vec<u32>);
+        fn wins(
+            rules: &Rules,
+            totals: &[u128],
+            state: State,
+            memo: &mut HashMap<State, Vec<u128>>,
+        ) -> Vec<u128> {
+            if let Some(wins) = memo.get(&state) {
+                return wins.clone();
+            }
+            let (player, positions, scores) = state.clone();
+            let mut result = vec![0; positions.len()];
+            for (total, &ways) in totals.iter().enumerate().filter(|(_, w)| **w > 0) {
+                let (mut positions, mut scores) = (positions.clone(), scores.clone());
+                positions[player] = rules.landing(positions[player], total as u32);
+                scores[player] += positions[player];
+                if scores[player] >= rules.target {
+                    result[player] += ways;
+                    continue;
+                }
+                let next = ((player + 1) % positions.len(), positions, scores);
+                for (sum, w) in result.iter_mut().zip(wins(rules, totals, next, memo)) {
+                    *sum += ways * w;
+                }
+            }
+            memo.insert(state, result.clone());
+            result
+        }
+        let state = (0, starts.to_vec(), vec![0; starts.len()]);
+        wins(rules, &rules.totals(), state, &mut HashMap::new())
+    }
+
+    #[test]
+    fn example() {
+        let outcome = play_deterministic(&Rules::DETERMINISTIC, &[4, 8]).unwrap();
+        assert_eq!(
+            outcome,
+            Outcome {
+                winner: 0,
+                scores: vec![1000, 745],
+                rolls: 993
+            }
+        );
+        assert_eq!(
+            count_wins(&Rules::DIRAC, &[4, 8]).unwrap(),
+            [444356092776315, 341960390180808]
+        );
+    }
+
+    #[test]
+    fn other_games() {
+        let rules = Rules {
+            board_size: 7,
+            die_faces: 2,
+            rolls_per_turn: 2,
+            target: 12,
+        };
+        for starts in [vec![1], vec![3, 3], vec![2, 7, 5], vec![1, 2, 3, 4]] {
+            assert_eq!(
+                count_wins(&rules, &starts).unwrap(),
+                brute_force(&rules, &starts),
+                "{:?}",
+                starts
+            );
+        }
+        // a one-faced die is as deterministic as it gets
+        let rules = Rules {
+            die_faces: 1,
+            ..rules
+        };
+        let outcome = play_deterministic(&rules, &[1, 4]).unwrap();
+        assert_eq!(outcome.rolls, 10);
+        let wins = count_wins(&rules, &[1, 4]).unwrap();
+        assert_eq!(wins, [1, 0]);
+
+        assert!(play_deterministic(&Rules::DIRAC, &[11]).is_err());
+        assert!(count_wins(&Rules::DIRAC, &[]).is_err());
+        assert!(
+            count_wins(
+                &Rules {
+                    target: 0,
+                    ..Rules::DIRAC
+                },
+                &[1]
+            )
+            .is_err()
+        );
+        // the universes outgrow even 128 bits with enough players
+        assert!(count_wins(&Rules::DIRAC, &[1; 12]).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use num::{PrimInt, Zero};
+
+pub mod polygon;
+pub mod registration;
+pub mod segment;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Vec2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Vec3<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+// A proper rotation of the cube, stored as a signed permutation matrix
+// with determinant 1. Rows are the output axes, columns the input ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rotation([[i8; 3]; 3]);
+
+// works for unsigned types too, unlike abs()
+fn abs_diff<T: PrimInt>(l: T, r: T) -> T {
+    if l > r { l - r } else { r - l }
+}
+
+impl<T> Vec2<T> {
+    pub const fn new(x: T, y: T) -> Self {
+        Self { x, y }
+    }
+}
+
+#[cfg(test)]
+impl<T: PrimInt> Vec2<T> {
+    pub fn manhattan_to(self, rhs: Self) -> T {
+        abs_diff(self.x, rhs.x) + abs_diff(self.y, rhs.y)
+    }
+
+    pub fn chebyshev_to(self, rhs: Self) -> T {
+        abs_diff(self.x, rhs.x).max(abs_diff(self.y, rhs.y))
+    }
+
+    pub fn distance2_to(self, rhs: Self) -> T {
+        let (dx, dy) = (abs_diff(self.x, rhs.x), abs_diff(self.y, rhs.y));
+        dx * dx + dy * dy
+    }
+}
+
+impl<T> Vec3<T> {
+    pub const fn new(x: T, y: T, z: T) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl<T: PrimInt> Vec3<T> {
+    pub fn manhattan_to(self, rhs: Self) -> T {
+        abs_diff(self.x, rhs.x) + abs_diff(self.y, rhs.y) + abs_diff(self.z, rhs.z)
+    }
+
+    #[cfg(test)]
+    pub fn chebyshev_to(self, rhs: Self) -> T {
+        abs_diff(self.x, rhs.x)
+            .max(abs_diff(self.y, rhs.y))
+            .max(abs_diff(self.z, rhs.z))
+    }
+
+    pub fn distance2_to(self, rhs: Self) -> T {
+        let dx = abs_diff(self.x, rhs.x);
+        let dy = abs_diff(self.y, rhs.y);
+        let dz = abs_diff(self.z, rhs.z);
+        dx * dx + dy * dy + dz * dz
+    }
+}
+
+macro_rules! impl_vec_ops {
+    ($vec:ident, $($field:ident),+) => {
+        impl<T: Add<Output = T>> Add for $vec<T> {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                Self { $($field: self.$field + rhs.$field),+ }
+            }
+        }
+
+        impl<T: Sub<Output = T>> Sub for $vec<T> {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                Self { $($field: self.$field - rhs.$field),+ }
+            }
+        }
+
+        impl<T: Neg<Output = T>> Neg for $vec<T> {
+            type Output = Self;
+
+            fn neg(self) -> Self::Output {
+                Self { $($field: -self.$field),+ }
+            }
+        }
+
+        impl<T: Mul<Output = T> + Copy> Mul<T> for $vec<T> {
+            type Output = Self;
+
+            fn mul(self, rhs: T) -> Self::Output {
+                Self { $($field: self.$field * rhs),+ }
+            }
+        }
+
+        impl<T: AddAssign> AddAssign for $vec<T> {
+            fn add_assign(&mut self, rhs: Self) {
+                $(self.$field += rhs.$field;)+
+            }
+        }
+
+        impl<T: SubAssign> SubAssign for $vec<T> {
+            fn sub_assign(&mut self, rhs: Self) {
+                $(self.$field -= rhs.$field;)+
+            }
+        }
+    };
+}
+
+impl_vec_ops!(Vec2, x, y);
+impl_vec_ops!(Vec3, x, y, z);
+
+impl Rotation {
+    pub const IDENTITY: Rotation = Rotation([[1, 0, 0], [0, 1, 0], [0, 0, 1]]);
+    pub const ALL: [Rotation; 24] = Self::generate_all();
+
+    // every permutation of the axes with every combination of signs,
+    // keeping the half that doesn't mirror (determinant 1)
+    const fn generate_all() -> [Rotation; 24] {
+        const PERMUTATIONS: [[usize; 3]; 6] = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        let mut all = [Self::IDENTITY; 24];
+        let mut found = 0;
+        let mut p = 0;
+        while p < PERMUTATIONS.len() {
+            let mut signs = 0;
+            while signs < 8 {
+                let mut matrix = [[0; 3]; 3];
+                let mut row = 0;
+                while row < 3 {
+                    matrix[row][PERMUTATIONS[p][row]] =
+                        if signs & (1 << row) == 0 { 1 } else { -1 };
+                    row += 1;
+                }
+                let rotation = Rotation(matrix);
+                if rotation.determinant() == 1 {
+                    all[found] = rotation;
+                    found += 1;
+                }
+                signs += 1;
+            }
+            p += 1;
+        }
+        all
+    }
+
+    const fn determinant(&self) -> i8 {
+        let m = &self.0;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    // for orthogonal matrices the inverse is the transpose
+    #[cfg(test)]
+    pub fn inverse(self) -> Self {
+        let m = self.0;
+        Rotation(std::array::from_fn(|r| std::array::from_fn(|c| m[c][r])))
+    }
+
+    // rotation that applies `self` first and then `next`
+    #[cfg(test)]
+    pub fn then(self, next: Self) -> Self {
+        next * self
+    }
+
+    pub fn apply<T>(self, v: Vec3<T>) -> Vec3<T>
+    where
+        T: Copy + Zero + Neg<Output = T>,
+    {
+        let scale = |factor: i8, value: T| match factor {
+            1 => value,
+            -1 => -value,
+            _ => T::zero(),
+        };
+        let row = |r: [i8; 3]| scale(r[0], v.x) + scale(r[1], v.y) + scale(r[2], v.z);
+        Vec3::new(row(self.0[0]), row(self.0[1]), row(self.0[2]))
+    }
+}
+
+impl Mul for Rotation {
+    type Output = Self;
+
+    // matrix product, so `(a * b).apply(v) == a.apply(b.apply(v))`
+    fn mul(self, rhs: Self) -> Self::Output {
+        let (l, r) = (self.0, rhs.0);
+        Rotation(std::array::from_fn(|row| {
+            std::array::from_fn(|col| (0..3).map(|k| l[row][k] * r[k][col]).sum())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn rotations_are_distinct_proper() {
+        let unique: HashSet<_> = Rotation::ALL.iter().collect();
+        assert_eq!(unique.len(), 24);
+        assert!(Rotation::ALL.iter().all(|r| r.determinant() == 1));
+        assert!(Rotation::ALL.contains(&Rotation::IDENTITY));
+    }
+
+    #[test]
+    fn rotations_form_a_group() {
+        for a in Rotation::ALL {
+            assert_eq!(a * a.inverse(), Rotation::IDENTITY);
+            assert_eq!(a.inverse() * a, Rotation::IDENTITY);
+            for b in Rotation::ALL {
+                assert!(Rotation::ALL.contains(&(a * b)));
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_composition() {
+        let v = Vec3::new(1, 2, 3);
+        let images: HashSet<_> = Rotation::ALL.iter().map(|r| r.apply(v)).collect();
+        assert_eq!(images.len(), 24);
+        for a in Rotation::ALL {
+            assert_eq!(a.inverse().apply(a.apply(v)), v);
+            for b in Rotation::ALL {
+                assert_eq!(a.then(b).apply(v), b.apply(a.apply(v)));
+            }
+        }
+    }
+
+    #[test]
+    fn distances() {
+        let (a, b) = (Vec3::new(1u32, 7, 3), Vec3::new(4u32, 2, 3));
+        assert_eq!(a.manhattan_to(b), 8);
+        assert_eq!(a.chebyshev_to(b), 5);
+        assert_eq!(a.distance2_to(b), 34);
+        let (c, d) = (Vec2::new(-2i64, 5), Vec2::new(3, -1));
+        assert_eq!(c.manhattan_to(d), 11);
+        assert_eq!(c.chebyshev_to(d), 6);
+        assert_eq!(c.distance2_to(d), 61);
+        assert_eq!(c + d, Vec2::new(1, 4));
+        assert_eq!(c - d, Vec2::new(-5, 6));
+        assert_eq!(-c * 2, Vec2::new(4, -10));
+    }
+}
@@ -0,0 +1,236 @@
+use ndarray::Array2;
+#[cfg(test)]
+use num::integer::gcd;
+
+use super::segment::Point;
+#[cfg(test)]
+use super::segment::Segment;
+
+// A simple polygon over lattice points, vertices in loop order (either winding).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+
+// Answers "is this axis-aligned rectangle fully inside" for a rectilinear
+// polygon in O(1). Coordinates are compressed so that even indices are the
+// polygon's own xs/ys and odd indices are the open gaps between them; every
+// compressed cell is then entirely inside or entirely outside.
+pub struct ContainmentIndex {
+    xs: Vec<i64>,
+    ys: Vec<i64>,
+    // prefix sums of compressed cells that hold lattice points outside the polygon
+    outside: Array2<u32>,
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<Point>) -> Self {
+        Polygon { vertices }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        let len = self.vertices.len();
+        (0..len).map(move |i| (self.vertices[i], self.vertices[(i + 1) % len]))
+    }
+
+    pub fn is_rectilinear(&self) -> bool {
+        self.edges().all(|(a, b)| a.x == b.x || a.y == b.y)
+    }
+
+    pub fn containment_index(&self) -> Option<ContainmentIndex> {
+        if !self.is_rectilinear() || self.vertices.len() < 4 {
+            return None;
+        }
+        let compress = |coord: fn(&Point) -> i64| {
+            let mut values: Vec<_> = self.vertices.iter().map(coord).collect();
+            values.sort_unstable();
+            values.dedup();
+            values
+        };
+        let (xs, ys) = (compress(|p| p.x), compress(|p| p.y));
+        // compressed row to doubled coordinate of a representative y,
+        // and whether that row has any lattice points at all
+        let representative = |i: usize| match i % 2 {
+            0 => (2 * ys[i / 2], true),
+            _ => {
+                let (lo, hi) = (ys[i / 2], ys[i / 2 + 1]);
+                (lo + hi, hi - lo > 1)
+            }
+        };
+        let col_has_points = |i: usize| i.is_multiple_of(2) || xs[i / 2 + 1] - xs[i / 2] > 1;
+        let col_of = |x: i64| 2 * xs.binary_search(&x).expect("Vertex x is compressed");
+        // (doubled y range, compressed column range)
+        let edges: Vec<_> = self
+            .edges()
+            .map(|(a, b)| {
+                let ys = (2 * a.y.min(b.y), 2 * a.y.max(b.y));
+                (ys, (col_of(a.x.min(b.x)), col_of(a.x.max(b.x))))
+            })
+            .collect();
+
+        let (width, height) = (2 * xs.len() - 1, 2 * ys.len() - 1);
+        let mut outside = Array2::zeros((height + 1, width + 1));
+        let mut boundary = vec![false; width];
+        let mut crossings = vec![false; width];
+        for row in 0..height {
+            let (y, row_has_points) = representative(row);
+            boundary.fill(false);
+            crossings.fill(false);
+            for &((y_lo, y_hi), (col_lo, col_hi)) in edges.iter() {
+                if (y_lo..=y_hi).contains(&y) {
+                    boundary[col_lo..=col_hi].fill(true);
+                }
+                // vertical edges crossing the row, half-open so vertices count once
+                if col_lo == col_hi && (y_lo..y_hi).contains(&y) {
+                    crossings[col_lo] ^= true;
+                }
+            }
+
+            // parity of crossings to the right of each column, walking right to left
+            let mut inside = false;
+            for col in (0..width).rev() {
+                let bad = !(boundary[col] || inside) && row_has_points && col_has_points(col);
+                outside[[row + 1, col + 1]] = bad as u32;
+                inside ^= crossings[col];
+            }
+            for col in 0..width {
+                outside[[row + 1, col + 1]] +=
+                    outside[[row, col + 1]] + outside[[row + 1, col]] - outside[[row, col]];
+            }
+        }
+
+        Some(ContainmentIndex { xs, ys, outside })
+    }
+}
+
+// Only the tests use these
+#[cfg(test)]
+impl Polygon {
+    // shoelace formula, doubled so it stays integral
+    pub fn area2(&self) -> i64 {
+        self.edges()
+            .map(|(a, b)| a.x * b.y - a.y * b.x)
+            .sum::<i64>()
+            .abs()
+    }
+
+    pub fn boundary_points(&self) -> i64 {
+        self.edges().map(|(a, b)| gcd(b.x - a.x, b.y - a.y)).sum()
+    }
+
+    // Pick's theorem: A = I + B/2 - 1
+    pub fn interior_points(&self) -> i64 {
+        (self.area2() - self.boundary_points() + 2) / 2
+    }
+
+    pub fn lattice_points(&self) -> i64 {
+        self.interior_points() + self.boundary_points()
+    }
+
+    // boundary counts as inside
+    pub fn contains(&self, point: Point) -> bool {
+        let mut inside = false;
+        for (a, b) in self.edges() {
+            if Segment::new(a, b).contains(point) {
+                return true;
+            }
+            // even-odd rule with a ray towards +x, half-open in y
+            if (a.y > point.y) != (b.y > point.y) {
+                let lhs = (point.x - a.x) * (b.y - a.y);
+                let rhs = (point.y - a.y) * (b.x - a.x);
+                if (b.y > a.y && lhs < rhs) || (b.y < a.y && lhs > rhs) {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+}
+
+impl ContainmentIndex {
+    // compressed index of a coordinate, None if it's outside the polygon's extent
+    fn compressed(values: &[i64], value: i64) -> Option<usize> {
+        match values.binary_search(&value) {
+            Ok(i) => Some(2 * i),
+            Err(i) if i == 0 || i == values.len() => None,
+            Err(i) => Some(2 * i - 1),
+        }
+    }
+
+    pub fn contains_rect(&self, corner1: Point, corner2: Point) -> bool {
+        let xs = (corner1.x.min(corner2.x), corner1.x.max(corner2.x));
+        let ys = (corner1.y.min(corner2.y), corner1.y.max(corner2.y));
+        let (Some(x1), Some(x2), Some(y1), Some(y2)) = (
+            Self::compressed(&self.xs, xs.0),
+            Self::compressed(&self.xs, xs.1),
+            Self::compressed(&self.ys, ys.0),
+            Self::compressed(&self.ys, ys.1),
+        ) else {
+            return false;
+        };
+        let o = &self.outside;
+        let bad = o[[y2 + 1, x2 + 1]] + o[[y1, x1]] - o[[y1, x2 + 1]] - o[[y2 + 1, x1]];
+        bad == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::iproduct;
+
+    use super::*;
+
+    fn polygon(points: &[(i64, i64)]) -> Polygon {
+        Polygon::new(points.iter().map(|&(x, y)| Point::new(x, y)).collect())
+    }
+
+    #[test]
+    fn picks_theorem() {
+        let triangle = polygon(&[(0, 0), (4, 0), (0, 3)]);
+        assert_eq!(triangle.area2(), 12);
+        assert_eq!(triangle.boundary_points(), 8);
+        assert_eq!(triangle.interior_points(), 3);
+        let square = polygon(&[(0, 0), (0, 2), (2, 2), (2, 0)]);
+        assert_eq!(square.area2(), 8);
+        assert_eq!(square.lattice_points(), 9);
+    }
+
+    #[test]
+    fn point_in_polygon() {
+        let triangle = polygon(&[(0, 0), (4, 0), (0, 3)]);
+        let inside: Vec<_> = iproduct!(-1..6, -1..5)
+            .filter(|&(x, y)| triangle.contains(Point::new(x, y)))
+            .collect();
+        assert_eq!(inside.len() as i64, triangle.lattice_points());
+        assert!(triangle.contains(Point::new(1, 1)));
+        assert!(triangle.contains(Point::new(4, 0)));
+        assert!(!triangle.contains(Point::new(3, 1)));
+    }
+
+    #[test]
+    fn rect_containment_matches_brute_force() {
+        // a U shape with a one wide gap
+        let shape = polygon(&[
+            (0, 0),
+            (9, 0),
+            (9, 8),
+            (6, 8),
+            (6, 3),
+            (4, 3),
+            (4, 8),
+            (0, 8),
+        ]);
+        let index = shape.containment_index().unwrap();
+        for (x1, y1, x2, y2) in iproduct!(-1..11, -1..10, -1..11, -1..10) {
+            let brute = iproduct!(x1.min(x2)..=x1.max(x2), y1.min(y2)..=y1.max(y2))
+                .all(|(x, y)| shape.contains(Point::new(x, y)));
+            let (c1, c2) = (Point::new(x1, y1), Point::new(x2, y2));
+            assert_eq!(index.contains_rect(c1, c2), brute, "{:?} {:?}", c1, c2);
+        }
+        assert!(
+            polygon(&[(0, 0), (4, 0), (0, 3)])
+                .containment_index()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::{Itertools, iproduct};
+
+use super::{Rotation, Vec3};
+
+pub type Point = Vec3<i32>;
+
+// (manhattan distance, squared euclidean distance) between two points, which
+// don't change however the cloud is turned or moved
+type Fingerprint = (i32, i32);
+
+// How much two clouds have to share before they're taken to overlap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settings {
+    // points that have to land on each other
+    pub min_overlap: usize,
+    // pair distances the clouds must have in common before trying any
+    // rotation, every pair of overlapping points gives one
+    pub min_shared_distances: usize,
+}
+
+// Where a cloud is and how it's turned relative to the first one, so a
+// point p of it is at `position + rotation.apply(p)` there
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pose {
+    pub position: Point,
+    pub rotation: Rotation,
+}
+
+#[derive(Clone, Debug)]
+pub struct Registration {
+    // indexed like the clouds, None for those that didn't line up with the rest
+    pub poses: Vec<Option<Pose>>,
+    // every point of the aligned clouds in the first one's coordinates
+    pub merged: Vec<Point>,
+}
+
+// The points merged so far with the pairs at every distance between them
+struct Cloud {
+    points: Vec<Point>,
+    known: HashSet<Point>,
+    pairs: HashMap<Fingerprint, Vec<(usize, usize)>>,
+}
+
+impl Settings {
+    pub fn with_overlap(min_overlap: usize) -> Self {
+        Settings {
+            min_overlap,
+            min_shared_distances: min_overlap * min_overlap.saturating_sub(1) / 2,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings::with_overlap(12)
+    }
+}
+
+impl Pose {
+    pub const ORIGIN: Pose = Pose {
+        position: Vec3 { x: 0, y: 0, z: 0 },
+        rotation: Rotation::IDENTITY,
+    };
+
+    pub fn apply(&self, point: Point) -> Point {
+        self.position + self.rotation.apply(point)
+    }
+}
+
+impl Registration {
+    pub fn is_complete(&self) -> bool {
+        self.poses.iter().all(Option::is_some)
+    }
+
+    pub fn unmatched(&self) -> impl Iterator<Item = usize> + '_ {
+        self.poses.iter().positions(Option::is_none)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Point> + Clone + '_ {
+        self.poses.iter().flatten().map(|pose| pose.position)
+    }
+
+    // Largest manhattan distance between two aligned clouds' origins
+    pub fn max_manhattan_distance(&self) -> Option<i32> {
+        self.positions()
+            .tuple_combinations()
+            .map(|(a, b)| a.manhattan_to(b))
+            .max()
+    }
+}
+
+fn fingerprint(p1: Point, p2: Point) -> Fingerprint {
+    (p1.manhattan_to(p2), p1.distance2_to(p2))
+}
+
+fn fingerprints(points: &[Point]) -> HashMap<Fingerprint, Vec<(usize, usize)>> {
+    let mut pairs: HashMap<_, Vec<_>> = HashMap::new();
+    for ((i1, p1), (i2, p2)) in points.iter().enumerate().tuple_combinations() {
+        pairs
+            .entry(fingerprint(*p1, *p2))
+            .or_default()
+            .push((i1, i2));
+    }
+    pairs
+}
+
+impl Cloud {
+    fn new(points: &[Point]) -> Self {
+        Cloud {
+            points: points.to_vec(),
+            known: points.iter().copied().collect(),
+            pairs: fingerprints(points),
+        }
+    }
+
+    fn add(&mut self, point: Point) {
+        if !self.known.insert(point) {
+            return;
+        }
+        let new_i = self.points.len();
+        for (i, p) in self.points.iter().enumerate() {
+            self.pairs
+                .entry(fingerprint(*p, point))
+                .or_default()
+                .push((i, new_i));
+        }
+        self.points.push(point);
+    }
+
+    fn overlaps(&self, points: &[Point], pose: Pose, settings: &Settings) -> bool {
+        let hits = points
+            .iter()
+            .filter(|&&p| self.known.contains(&pose.apply(p)));
+        hits.take(settings.min_overlap).count() == settings.min_overlap
+    }
+
+    // The pose that puts enough of `other` on this cloud, if there's one
+    fn align(&self, other: &Cloud, settings: &Settings) -> Option<Pose> {
+        let shared: Vec<_> = self
+            .pairs
+            .keys()
+            .filter(|fp| other.pairs.contains_key(fp))
+            .collect();
+        if shared.len() < settings.min_shared_distances {
+            return None;
+        }
+
+        for fp in shared {
+            let pairs = iproduct!(&self.pairs[fp], &other.pairs[fp]);
+            for (&(a1, a2), &(b1, b2)) in pairs {
+                let (a1, a2) = (self.points[a1], self.points[a2]);
+                // the pair can match either way round
+                for (b1, b2) in [(b1, b2), (b2, b1)] {
+                    let (b1, b2) = (other.points[b1], other.points[b2]);
+                    let rotations = Rotation::ALL
+                        .into_iter()
+                        .filter(|rot| a1 - a2 == rot.apply(b1 - b2));
+                    for rotation in rotations {
+                        let pose = Pose {
+                            position: a1 - rotation.apply(b1),
+                            rotation,
+                        };
+                        if self.overlaps(&other.points, pose, settings) {
+                            return Some(pose);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+// Lines every cloud up with the first one, growing it with each cloud that
+// fits until none of the rest do
+pub fn register(clouds: &[Vec<Point>], settings: &Settings) -> Registration {
+    let mut poses = vec![None; clouds.len()];
+    let Some(first) = clouds.first() else {
+        return Registration {
+            poses,
+            merged: Vec::new(),
+        };
+    };
+    poses[0] = Some(Pose::ORIGIN);
+    let mut merged = Cloud::new(first);
+    let mut remaining: Vec<_> = clouds
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, points)| (i, Cloud::new(points)))
+        .collect();
+
+    while let Some((found, pose)) = remaining
+        .iter()
+        .enumerate()
+        .find_map(|(r, (_, cloud))| merged.align(cloud, settings).map(|pose| (r, pose)))
+    {
+        let (i, cloud) = remaining.remove(found);
+        for &point in &cloud.points {
+            merged.add(pose.apply(point));
+        }
+        poses[i] = Some(pose);
+    }
+
+    Registration {
+        poses,
+        merged: merged.points,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cloud(points: &[(i32, i32, i32)]) -> Vec<Point> {
+        points
+            .iter()
+            .map(|&(x, y, z)| Point::new(x, y, z))
+            .collect()
+    }
+
+    // the clouds a scanner at the origin and one at `pose` see of `points`
+    fn seen_from(points: &[Point], pose: &Pose) -> Vec<Vec<Point>> {
+        let inverse = pose.rotation.inverse();
+        let seen = points.iter().map(|&p| inverse.apply(p - pose.position));
+        vec![points.to_vec(), seen.collect()]
+    }
+
+    #[test]
+    fn recovers_poses() {
+        let shared = cloud(&[
+            (0, 0, 0),
+            (5, 1, 2),
+            (9, -3, 4),
+            (-2, 7, 1),
+            (3, 3, -8),
+            (11, 0, 6),
+        ]);
+        let truth = Pose {
+            position: Point::new(20, -7, 3),
+            rotation: Rotation::ALL[17],
+        };
+        let mut clouds = seen_from(&shared, &truth);
+        clouds[1].extend(cloud(&[(40, 40, 40), (41, 45, 43)]));
+        clouds.push(cloud(&[(1, 2, 3), (100, 200, 300)]));
+
+        let settings = Settings::with_overlap(6);
+        assert_eq!(settings.min_shared_distances, 15);
+        let registration = register(&clouds, &settings);
+        assert_eq!(registration.poses, [Some(Pose::ORIGIN), Some(truth), None]);
+        assert!(!registration.is_complete());
+        assert_eq!(registration.unmatched().collect_vec(), [2]);
+        assert_eq!(registration.merged.len(), shared.len() + 2);
+        assert_eq!(registration.max_manhattan_distance(), Some(30));
+
+        // asking for more overlap than there is leaves it unmatched
+        let strict = register(&clouds[..2], &Settings::with_overlap(7));
+        assert_eq!(strict.unmatched().collect_vec(), [1]);
+        assert_eq!(strict.merged, shared);
+        assert_eq!(strict.max_manhattan_distance(), None);
+    }
+}
@@ -0,0 +1,306 @@
+use std::ops::RangeInclusive;
+
+use num::integer::gcd;
+
+use super::Vec2;
+use crate::traits::Intersect;
+
+pub type Point = Vec2<i64>;
+
+// A closed segment between two lattice points, of any slope.
+// Because both ends are lattice points every lattice point on the segment is
+// `start + k * step` for `k` in `0..=steps`, with `step` the primitive direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Segment {
+    start: Point,
+    end: Point,
+    step: Point,
+    steps: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentIntersection {
+    Point(Point),
+    Overlap(Segment),
+}
+
+fn cross(l: Point, r: Point) -> i64 {
+    l.x * r.y - l.y * r.x
+}
+
+fn dot(l: Point, r: Point) -> i64 {
+    l.x * r.x + l.y * r.y
+}
+
+impl Segment {
+    pub fn new(start: Point, end: Point) -> Self {
+        let diff = end - start;
+        let steps = gcd(diff.x, diff.y);
+        let step = match steps {
+            0 => Point::new(0, 0),
+            _ => Point::new(diff.x / steps, diff.y / steps),
+        };
+        Segment {
+            start,
+            end,
+            step,
+            steps,
+        }
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        self.start.y == self.end.y
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        self.start.x == self.end.x
+    }
+
+    pub fn is_axis_aligned(&self) -> bool {
+        self.is_horizontal() || self.is_vertical()
+    }
+
+    #[cfg(test)]
+    pub fn is_diagonal(&self) -> bool {
+        self.step.x.abs() == 1 && self.step.y.abs() == 1
+    }
+
+    #[cfg(test)]
+    pub fn lattice_len(&self) -> usize {
+        self.steps as usize + 1
+    }
+
+    #[cfg(test)]
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        (0..=self.steps).map(|k| self.at(k))
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        self.param_of(point).is_some()
+    }
+
+    fn at(&self, k: i64) -> Point {
+        self.start + self.step * k
+    }
+
+    // k such that `self.at(k) == point`, if that point is on the segment
+    fn param_of(&self, point: Point) -> Option<i64> {
+        let offset = point - self.start;
+        if self.steps == 0 {
+            return (offset == Point::new(0, 0)).then_some(0);
+        }
+        if cross(self.step, offset) != 0 {
+            return None;
+        }
+        let k = dot(self.step, offset) / dot(self.step, self.step);
+        (0..=self.steps).contains(&k).then_some(k)
+    }
+
+    fn xs(&self) -> RangeInclusive<i64> {
+        self.start.x.min(self.end.x)..=self.start.x.max(self.end.x)
+    }
+
+    // lattice points of the segment in column x, as a range of y
+    fn ys_at(&self, x: i64) -> Option<RangeInclusive<i64>> {
+        if !self.xs().contains(&x) {
+            None
+        } else if self.step.x == 0 {
+            Some(self.start.y.min(self.end.y)..=self.start.y.max(self.end.y))
+        } else if (x - self.start.x) % self.step.x != 0 {
+            None
+        } else {
+            let y = self.at((x - self.start.x) / self.step.x).y;
+            Some(y..=y)
+        }
+    }
+
+    fn collinear_intersection(&self, other: &Self) -> Option<SegmentIntersection> {
+        if self.steps == 0 {
+            return other
+                .contains(self.start)
+                .then_some(SegmentIntersection::Point(self.start));
+        }
+        // other's ends are lattice points on our line, so they're at whole steps
+        let project = |p: Point| dot(self.step, p - self.start) / dot(self.step, self.step);
+        let (k1, k2) = (project(other.start), project(other.end));
+        let lo = k1.min(k2).max(0);
+        let hi = k1.max(k2).min(self.steps);
+        match lo.cmp(&hi) {
+            std::cmp::Ordering::Greater => None,
+            std::cmp::Ordering::Equal => Some(SegmentIntersection::Point(self.at(lo))),
+            std::cmp::Ordering::Less => Some(SegmentIntersection::Overlap(Segment::new(
+                self.at(lo),
+                self.at(hi),
+            ))),
+        }
+    }
+}
+
+impl Intersect for Segment {
+    type Output = SegmentIntersection;
+
+    fn intersect_with(&self, other: &Self) -> Option<Self::Output> {
+        if other.steps == 0 {
+            return self
+                .contains(other.start)
+                .then_some(SegmentIntersection::Point(other.start));
+        }
+        let (r, s) = (self.end - self.start, other.end - other.start);
+        let offset = other.start - self.start;
+        let denom = cross(r, s);
+        if denom == 0 {
+            if cross(offset, s) != 0 {
+                return None; // parallel, on different lines
+            }
+            return self.collinear_intersection(other);
+        }
+
+        // self.start + r * t == other.start + s * u, both t and u in [0, 1]
+        let (t_num, u_num) = (cross(offset, s), cross(offset, r));
+        let (t_num, u_num, denom) = match denom < 0 {
+            true => (-t_num, -u_num, -denom),
+            false => (t_num, u_num, denom),
+        };
+        if !(0..=denom).contains(&t_num) || !(0..=denom).contains(&u_num) {
+            return None;
+        }
+        let (x_num, y_num) = (r.x * t_num, r.y * t_num);
+        if x_num % denom != 0 || y_num % denom != 0 {
+            return None; // crossing is not on a lattice point
+        }
+        Some(SegmentIntersection::Point(
+            self.start + Point::new(x_num / denom, y_num / denom),
+        ))
+    }
+}
+
+// Number of lattice points that lie on at least `k` of the segments.
+// Sweeps a vertical line over the columns, keeping only the segments
+// spanning the current column and counting overlaps on it with 1D events.
+pub fn count_covered(segments: &[Segment], k: usize) -> usize {
+    let mut pending: Vec<_> = segments.iter().collect();
+    pending.sort_unstable_by_key(|s| std::cmp::Reverse(*s.xs().start()));
+    let mut active: Vec<&Segment> = Vec::new();
+    let mut events = Vec::new();
+    let mut covered = 0;
+    let mut x = i64::MIN;
+
+    while !(active.is_empty() && pending.is_empty()) {
+        if active.is_empty() {
+            x = x.max(*pending.last().unwrap().xs().start());
+        }
+        while pending.last().is_some_and(|s| *s.xs().start() == x) {
+            active.push(pending.pop().unwrap());
+        }
+
+        events.clear();
+        for ys in active.iter().filter_map(|s| s.ys_at(x)) {
+            events.push((*ys.start(), 1));
+            events.push((*ys.end() + 1, -1));
+        }
+        events.sort_unstable();
+        let mut depth = 0;
+        for (i, &(y, delta)) in events.iter().enumerate() {
+            depth += delta;
+            if let Some(&(next_y, _)) = events.get(i + 1)
+                && depth >= k as i32
+            {
+                covered += (next_y - y) as usize;
+            }
+        }
+
+        active.retain(|s| *s.xs().end() > x);
+        x += 1;
+    }
+    covered
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn seg(x1: i64, y1: i64, x2: i64, y2: i64) -> Segment {
+        Segment::new(Point::new(x1, y1), Point::new(x2, y2))
+    }
+
+    fn brute_count(segments: &[Segment], k: usize) -> usize {
+        let mut counts: HashMap<Point, usize> = HashMap::new();
+        for p in segments.iter().flat_map(|s| s.points()) {
+            *counts.entry(p).or_default() += 1;
+        }
+        counts.values().filter(|&&c| c >= k).count()
+    }
+
+    #[test]
+    fn lattice_points() {
+        let s = seg(0, 0, 6, 3);
+        assert_eq!(s.lattice_len(), 4);
+        assert_eq!(
+            s.points().collect::<Vec<_>>(),
+            [(0, 0), (2, 1), (4, 2), (6, 3)].map(|(x, y)| Point::new(x, y))
+        );
+        assert!(s.contains(Point::new(4, 2)));
+        assert!(!s.contains(Point::new(3, 1)));
+        assert!(!s.contains(Point::new(8, 4)));
+    }
+
+    #[test]
+    fn crossing() {
+        use SegmentIntersection::Point as P;
+        let cross = |a: Segment, b: Segment| a.intersect_with(&b);
+        assert_eq!(
+            cross(seg(0, 0, 4, 4), seg(0, 4, 4, 0)),
+            Some(P(Point::new(2, 2)))
+        );
+        // crosses at (0.5, 0.5)
+        assert_eq!(cross(seg(0, 0, 1, 1), seg(0, 1, 1, 0)), None);
+        assert_eq!(
+            cross(seg(0, 0, 6, 3), seg(4, 0, 4, 9)),
+            Some(P(Point::new(4, 2)))
+        );
+        assert_eq!(cross(seg(0, 0, 3, 3), seg(5, 0, 5, 9)), None);
+        assert_eq!(
+            cross(seg(0, 0, 3, 3), seg(3, 3, 3, 3)),
+            Some(P(Point::new(3, 3)))
+        );
+    }
+
+    #[test]
+    fn overlapping() {
+        use SegmentIntersection::{Overlap, Point as P};
+        assert_eq!(
+            seg(0, 0, 6, 3).intersect_with(&seg(8, 4, 2, 1)),
+            Some(Overlap(seg(2, 1, 6, 3)))
+        );
+        assert_eq!(
+            seg(0, 0, 6, 3).intersect_with(&seg(6, 3, 10, 5)),
+            Some(P(Point::new(6, 3)))
+        );
+        assert_eq!(seg(0, 0, 2, 1).intersect_with(&seg(4, 2, 6, 3)), None);
+        assert_eq!(seg(0, 0, 4, 0).intersect_with(&seg(0, 1, 4, 1)), None);
+    }
+
+    #[test]
+    fn covered_matches_brute_force() {
+        let segments = [
+            seg(0, 9, 5, 9),
+            seg(8, 0, 0, 8),
+            seg(9, 4, 3, 4),
+            seg(2, 2, 2, 1),
+            seg(7, 0, 7, 4),
+            seg(6, 4, 2, 0),
+            seg(0, 9, 2, 9),
+            seg(3, 4, 1, 4),
+            seg(0, 0, 8, 8),
+            seg(5, 5, 8, 2),
+            seg(0, 0, 6, 3),
+            seg(-3, 7, 9, -2),
+            seg(4, -1, 4, 6),
+        ];
+        for k in 1..=4 {
+            assert_eq!(count_covered(&segments, k), brute_count(&segments, k));
+        }
+    }
+}
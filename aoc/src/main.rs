@@ -1,12 +1,47 @@
 use chrono::Datelike;
 use proc::run_year;
-use rules::parse_expect;
 
+mod alignment;
+mod alu;
+mod automaton;
+mod bingo;
+mod bits;
+mod brackets;
+mod diagnostic;
+mod dice;
+mod geometry;
+mod matrix;
 mod parse;
+mod polymer;
+mod population;
+mod rng;
+mod segments;
+mod snailfish;
 mod traits;
+mod trajectory;
 mod years;
 
 fn main() {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let command = match args.first().map(String::as_str) {
+        Some("alu") => Some(alu::repl::run as fn(&[String]) -> anyhow::Result<()>),
+        Some("burrow") => Some(years::year2021::day23::run_replay as _),
+        Some("snailfish") => Some(snailfish::cli::run as _),
+        Some("caves") => Some(years::year2021::day12::run_paths as _),
+        Some("lanternfish") => Some(years::year2021::day06::run_series as _),
+        Some("bingo") => Some(years::year2021::day04::run_game as _),
+        Some("packets") => Some(years::year2021::day16::run_decoder as _),
+        Some("crabs") => Some(years::year2021::day07::run_alignment as _),
+        _ => None,
+    };
+    if let Some(command) = command {
+        if let Err(e) = command(&args[1..]) {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     match chrono::Local::now().year() {
         2021 => run_year!(2021, 25),
         2022 => run_year!(2022, 05),
@@ -0,0 +1,70 @@
+use num::{CheckedAdd, CheckedMul, One, Zero};
+
+// Square matrices of counts, row major. The arithmetic is checked so the
+// same code runs on fixed width integers and on BigUint, and a count that
+// doesn't fit comes back as None.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Matrix<C> {
+    size: usize,
+    cells: Vec<C>,
+}
+
+impl<C> Matrix<C>
+where
+    C: Zero + One + Clone + CheckedAdd + CheckedMul,
+{
+    pub fn zeros(size: usize) -> Self {
+        Matrix {
+            size,
+            cells: vec![C::zero(); size * size],
+        }
+    }
+
+    pub fn add_one(&mut self, row: usize, column: usize) -> Option<()> {
+        let cell = &mut self.cells[row * self.size + column];
+        *cell = cell.checked_add(&C::one())?;
+        Some(())
+    }
+
+    // The row vector times the matrix
+    pub fn times(&self, vector: &[C]) -> Option<Vec<C>> {
+        let n = self.size;
+        let mut result = vec![C::zero(); n];
+        for (i, v) in vector.iter().enumerate().filter(|(_, v)| !v.is_zero()) {
+            for (j, m) in self.cells[i * n..(i + 1) * n].iter().enumerate() {
+                if !m.is_zero() {
+                    result[j] = result[j].checked_add(&v.checked_mul(m)?)?;
+                }
+            }
+        }
+        Some(result)
+    }
+
+    pub fn squared(&self) -> Option<Self> {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for row in self.cells.chunks(self.size) {
+            cells.extend(self.times(row)?);
+        }
+        Some(Matrix {
+            size: self.size,
+            cells,
+        })
+    }
+
+    // The row vector times the matrix to the given power, by squaring
+    pub fn times_power(&self, vector: &[C], exponent: u64) -> Option<Vec<C>> {
+        let mut vector = vector.to_vec();
+        let mut power = self.clone();
+        let mut exponent_left = exponent;
+        while exponent_left > 0 {
+            if exponent_left & 1 == 1 {
+                vector = power.times(&vector)?;
+            }
+            exponent_left >>= 1;
+            if exponent_left > 0 {
+                power = power.squared()?;
+            }
+        }
+        Some(vector)
+    }
+}
@@ -1,12 +1,17 @@
-use std::ops::{AddAssign, Mul, MulAssign, Neg, RangeInclusive};
+use std::{
+    fmt::Display,
+    ops::{AddAssign, Mul, MulAssign, Neg, RangeInclusive},
+};
 
+use ndarray::Array2;
 use nom::{
-    Err, IResult,
-    bytes::complete::tag,
-    character::complete::{digit1, one_of},
-    combinator::{map_opt, opt},
-    error::{ErrorKind, ParseError},
-    sequence::separated_pair,
+    Err, IResult, Parser,
+    bytes::complete::{tag, take},
+    character::complete::{digit1, line_ending, not_line_ending, one_of, space0},
+    combinator::{eof, map_opt, opt},
+    error::{Error, ErrorKind, ParseError},
+    multi::{many0, many1, separated_list1},
+    sequence::{delimited, pair, separated_pair, terminated},
 };
 
 pub fn ascii_digit_to_value(character: u8) -> Option<u8> {
@@ -106,3 +111,299 @@ where
     let (rest, (from, to)) = separated_pair(parse_signed, tag(b".."), parse_signed)(input)?;
     Ok((rest, from..=to))
 }
+
+// Parser errors that can tell where they happened
+pub trait ErrorInput<I> {
+    fn input(&self) -> &I;
+}
+
+impl<I> ErrorInput<I> for Error<I> {
+    fn input(&self) -> &I {
+        &self.input
+    }
+}
+
+// Lines separated by "\n" or "\r\n", trailing newlines are left unconsumed.
+// The list ends at the first line the parser can't even start on, a line
+// it gives up on halfway (or doesn't finish) fails the whole parse there.
+pub fn lines<'a, O, E, F>(mut line: F) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<O>, E>
+where
+    E: ParseError<&'a [u8]> + ErrorInput<&'a [u8]>,
+    F: Parser<&'a [u8], O, E>,
+{
+    move |input| {
+        let (mut rest, first) = line.parse(input)?;
+        let mut parsed = vec![first];
+        loop {
+            let next = match line_ending::<_, E>(rest) {
+                Ok((next, _)) => next,
+                Err(Err::Error(_)) if rest.is_empty() => return Ok((rest, parsed)),
+                Err(Err::Error(e)) => return Err(Err::Failure(e)),
+                Err(e) => return Err(e),
+            };
+            match line.parse(next) {
+                Ok((remaining, value)) => {
+                    parsed.push(value);
+                    rest = remaining;
+                }
+                Err(Err::Error(e)) if e.input().len() == next.len() => return Ok((rest, parsed)),
+                Err(Err::Error(e)) => return Err(Err::Failure(e)),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+// Blocks separated by one or more blank lines
+pub fn paragraphs<'a, O, E, F>(paragraph: F) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<O>, E>
+where
+    E: ParseError<&'a [u8]>,
+    F: Parser<&'a [u8], O, E>,
+{
+    separated_list1(pair(line_ending, many1(line_ending)), paragraph)
+}
+
+// Runs the parser over the whole input, allowing only newlines after it
+pub fn whole_input<'a, O, E, F>(parser: F) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O, E>
+where
+    E: ParseError<&'a [u8]>,
+    F: Parser<&'a [u8], O, E>,
+{
+    terminated(parser, pair(many0(line_ending), eof))
+}
+
+// Every integer on the line, whatever is between them. A '-' directly after
+// a digit is taken as a separator (as in "2-4"), otherwise it's a sign.
+#[cfg(test)]
+pub fn line_integers<'a, I, E>(input: &'a [u8]) -> IResult<&'a [u8], Vec<I>, E>
+where
+    E: ParseError<&'a [u8]>,
+    I: AddAssign<I> + MulAssign<I> + Mul<I, Output = I> + From<u8> + Neg<Output = I>,
+{
+    let (rest, line) = not_line_ending(input)?;
+    let mut numbers = Vec::new();
+    let mut i = 0;
+    while i < line.len() {
+        let after_digit = i > 0 && line[i - 1].is_ascii_digit();
+        let starts_number = line[i].is_ascii_digit()
+            || (!after_digit
+                && matches!(line[i], b'-' | b'+')
+                && line.get(i + 1).is_some_and(u8::is_ascii_digit));
+        if !starts_number {
+            i += 1;
+            continue;
+        }
+        let (remaining, number) = signed_parser_radix::<I, E>(10)(&line[i..])?;
+        numbers.push(number);
+        i = line.len() - remaining.len();
+    }
+    Ok((rest, numbers))
+}
+
+// Splits a line into columns of the given widths, the last columns
+// may be cut short (or empty) if the line ends early
+pub fn fixed_width<'a, E>(
+    widths: &[usize],
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Vec<&'a [u8]>, E>
+where
+    E: ParseError<&'a [u8]>,
+{
+    let widths = widths.to_vec();
+    move |input| {
+        let (rest, mut line) = not_line_ending(input)?;
+        let mut columns = Vec::with_capacity(widths.len());
+        for &width in widths.iter() {
+            let (remaining, column) = take(width.min(line.len()))(line)?;
+            columns.push(column);
+            line = remaining;
+        }
+        Ok((rest, columns))
+    }
+}
+
+// Lines like "AB -> C", spaces around the separator are optional
+pub fn key_value<'a, K, V, E, FK, FV>(
+    key: FK,
+    separator: &'static str,
+    value: FV,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], (K, V), E>
+where
+    E: ParseError<&'a [u8]>,
+    FK: Parser<&'a [u8], K, E>,
+    FV: Parser<&'a [u8], V, E>,
+{
+    separated_pair(key, delimited(space0, tag(separator), space0), value)
+}
+
+// A rectangular grid of single byte cells
+pub fn grid<'a, T, E, F>(cell: F) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Array2<T>, E>
+where
+    E: ParseError<&'a [u8]> + ErrorInput<&'a [u8]>,
+    F: Fn(u8) -> Option<T> + Copy,
+{
+    move |input| {
+        let cell_parser = map_opt(take(1usize), move |ch: &[u8]| cell(ch[0]));
+        let (rest, rows) = lines(many1(cell_parser))(input)?;
+        let shape = (rows.len(), rows[0].len());
+        let cells = rows.into_iter().flatten().collect();
+        match Array2::from_shape_vec(shape, cells) {
+            Ok(array) => Ok((rest, array)),
+            Err(_) => Err(Err::Error(E::from_error_kind(input, ErrorKind::Verify))),
+        }
+    }
+}
+
+// Where and why a nom parser gave up, with the offending line for context
+#[derive(Debug)]
+pub struct ParseDiagnostic {
+    line: usize,
+    column: usize,
+    text: String,
+    expected: String,
+}
+
+impl ParseDiagnostic {
+    pub fn new(file: &[u8], err: Err<Error<&[u8]>>) -> Self {
+        let (remaining, expected) = match err {
+            Err::Error(e) | Err::Failure(e) => (e.input, e.code.description().to_owned()),
+            Err::Incomplete(_) => (&file[file.len()..], "more input".to_owned()),
+        };
+        // the failing input is normally a subslice of the file, but don't rely on it
+        let offset = (remaining.as_ptr() as usize)
+            .checked_sub(file.as_ptr() as usize)
+            .filter(|&o| o <= file.len())
+            .unwrap_or(file.len());
+
+        let line_start = file[..offset]
+            .iter()
+            .rposition(|&c| c == b'\n')
+            .map_or(0, |i| i + 1);
+        let line_end = file[offset..]
+            .iter()
+            .position(|&c| c == b'\n')
+            .map_or(file.len(), |i| offset + i);
+        let text = String::from_utf8_lossy(&file[line_start..line_end])
+            .trim_end_matches('\r')
+            .to_owned();
+
+        ParseDiagnostic {
+            line: file[..line_start].iter().filter(|&&c| c == b'\n').count() + 1,
+            column: offset - line_start + 1,
+            text,
+            expected,
+        }
+    }
+}
+
+impl Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let gutter = self.line.to_string().len();
+        writeln!(
+            f,
+            "parsing failed at line {}, column {}: expected {}",
+            self.line, self.column, self.expected
+        )?;
+        writeln!(f, "{} | {}", self.line, self.text)?;
+        write!(f, "{:gutter$} | {:>col$}", "", "^", col = self.column)
+    }
+}
+
+impl std::error::Error for ParseDiagnostic {}
+
+// Turns a parser result into the parsed value or a ParseDiagnostic for `file`
+pub trait Diagnose<O> {
+    fn diagnose(self, file: &[u8]) -> anyhow::Result<O>;
+}
+
+impl<O> Diagnose<O> for IResult<&[u8], O> {
+    fn diagnose(self, file: &[u8]) -> anyhow::Result<O> {
+        self.map(|t| t.1)
+            .map_err(|e| ParseDiagnostic::new(file, e).into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nom::{character::complete::alpha1, error::Error};
+
+    use super::*;
+
+    type Res<'a, O> = IResult<&'a [u8], O, Error<&'a [u8]>>;
+
+    #[test]
+    fn lines_and_paragraphs() {
+        let input = b"1\r\n2\n\n\n3\r\n\r\n4\n5\n\n";
+        let res: Res<_> = whole_input(paragraphs(lines(parse_unsigned::<u32>)))(input);
+        assert_eq!(res.unwrap().1, [vec![1, 2], vec![3], vec![4, 5]]);
+    }
+
+    #[test]
+    fn integers_anywhere() {
+        let res: Res<Vec<i32>> = line_integers(b"target area: x=20..30, y=-10..-5\nnext");
+        assert_eq!(res.unwrap(), (&b"\nnext"[..], vec![20, 30, -10, -5]));
+        let res: Res<Vec<i32>> = line_integers(b"2-4,+6-8 p=<-1,0>");
+        assert_eq!(res.unwrap().1, [2, 4, 6, 8, -1, 0]);
+    }
+
+    #[test]
+    fn columns_and_rules() {
+        let res: Res<_> = fixed_width(&[4, 4, 3])(b"123 45   6\r\n");
+        assert_eq!(
+            res.unwrap(),
+            (&b"\r\n"[..], vec![&b"123 "[..], b"45  ", b" 6"])
+        );
+        let res: Res<_> = lines(key_value(alpha1, "->", alpha1))(b"AB -> C\nCH->B");
+        assert_eq!(
+            res.unwrap().1,
+            [(&b"AB"[..], &b"C"[..]), (&b"CH"[..], &b"B"[..])]
+        );
+    }
+
+    #[test]
+    fn diagnostics() {
+        let file = b"12,34\r\n56,x8\n";
+        let res: IResult<_, _> = whole_input(lines(separated_pair(
+            parse_unsigned::<u32>,
+            tag(","),
+            parse_unsigned::<u32>,
+        )))(file);
+        let err = res.diagnose(file).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            concat!(
+                "parsing failed at line 2, column 4: expected Digit\n",
+                "2 | 56,x8\n",
+                "  |    ^",
+            )
+        );
+
+        // a line that's left unfinished fails there too
+        let file = b"1 2\n3 4x\n";
+        let res: IResult<_, _> =
+            whole_input(lines(separated_list1(tag(" "), parse_unsigned::<u32>)))(file);
+        let err = ParseDiagnostic::new(file, res.unwrap_err());
+        assert_eq!((err.line, err.column), (2, 4));
+
+        let res: IResult<_, Vec<u32>> = lines(parse_unsigned)(b"");
+        let err = ParseDiagnostic::new(b"1\n\nab", res.unwrap_err());
+        assert_eq!((err.line, err.column), (3, 3));
+
+        let file = b"3\n4\n5,6";
+        let res =
+            separated_pair(parse_unsigned::<u32>, tag(","), parse_unsigned::<u32>)(&file[4..]);
+        assert!(res.diagnose(file).is_ok());
+        let res =
+            separated_pair(parse_unsigned::<u32>, tag(";"), parse_unsigned::<u32>)(&file[4..]);
+        let err = res.diagnose(file).unwrap_err().to_string();
+        assert!(err.starts_with("parsing failed at line 3, column 2: expected Tag\n"));
+    }
+
+    #[test]
+    fn grids() {
+        let digit = |c: u8| c.is_ascii_digit().then(|| c - b'0');
+        let res: Res<_> = grid(digit)(b"123\r\n456\n");
+        assert_eq!(res.unwrap().1, ndarray::arr2(&[[1, 2, 3], [4, 5, 6]]));
+        let res: Res<_> = grid(digit)(b"123\n45\n");
+        assert!(res.is_err());
+    }
+}
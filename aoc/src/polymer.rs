@@ -0,0 +1,215 @@
+use std::{collections::HashMap, ops::Sub};
+
+use anyhow::{Context, Result, bail};
+use num::{CheckedAdd, CheckedMul, One, Zero};
+
+use crate::matrix::Matrix;
+
+// Pair insertion on a polymer, tracked as how many times every pair of
+// elements appears. A step is a linear map on those counts, so any number of
+// steps is a power of one matrix.
+#[derive(Clone, Debug)]
+pub struct Polymer {
+    elements: Vec<u8>,
+    // as indices into elements
+    template: Vec<usize>,
+    // for every pair, the pairs it turns into in one step
+    produces: Vec<Vec<usize>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Histogram<C> {
+    // by element, in byte order
+    pub counts: Vec<(u8, C)>,
+}
+
+impl Polymer {
+    pub fn new(template: &[u8], rules: &[([u8; 2], u8)]) -> Result<Self> {
+        if template.is_empty() {
+            bail!("The template is empty");
+        }
+        let mut elements: Vec<u8> = template
+            .iter()
+            .chain(rules.iter().flat_map(|([a, b], c)| [a, b, c]))
+            .copied()
+            .collect();
+        elements.sort_unstable();
+        elements.dedup();
+        let index = |e: u8| elements.binary_search(&e).unwrap();
+        let n = elements.len();
+
+        let mut inserted = HashMap::new();
+        for &([a, b], c) in rules {
+            if let Some(other) = inserted.insert((index(a), index(b)), index(c))
+                && other != index(c)
+            {
+                bail!("Conflicting rules for {}{}", a as char, b as char);
+            }
+        }
+        let produces = (0..n * n)
+            .map(|pair| {
+                let (a, b) = (pair / n, pair % n);
+                match inserted.get(&(a, b)) {
+                    Some(&c) => vec![a * n + c, c * n + b],
+                    None => vec![pair],
+                }
+            })
+            .collect();
+        Ok(Polymer {
+            template: template.iter().map(|&e| index(e)).collect(),
+            elements,
+            produces,
+        })
+    }
+
+    fn pairs(&self) -> usize {
+        self.elements.len() * self.elements.len()
+    }
+
+    fn transition<C>(&self) -> Option<Matrix<C>>
+    where
+        C: Zero + One + Clone + CheckedAdd + CheckedMul,
+    {
+        let mut matrix = Matrix::zeros(self.pairs());
+        for (from, into) in self.produces.iter().enumerate() {
+            for &to in into {
+                matrix.add_one(from, to)?;
+            }
+        }
+        Some(matrix)
+    }
+
+    // Element counts after `steps` steps, an error if they don't fit in C
+    pub fn histogram<C>(&self, steps: u64) -> Result<Histogram<C>>
+    where
+        C: Zero + One + Clone + CheckedAdd + CheckedMul,
+    {
+        let width = self.elements.len();
+        let mut pairs = vec![C::zero(); self.pairs()];
+        for pair in self.template.windows(2) {
+            let count = &mut pairs[pair[0] * width + pair[1]];
+            *count = count.checked_add(&C::one()).context("Counts overflow")?;
+        }
+
+        let overflow = || format!("Counts overflow within {} steps", steps);
+        let transition = self.transition().with_context(overflow)?;
+        let pairs = transition
+            .times_power(&pairs, steps)
+            .with_context(overflow)?;
+
+        // every element but the first is the second half of a pair
+        let mut counts = vec![C::zero(); width];
+        counts[self.template[0]] = C::one();
+        for (pair, count) in pairs.iter().enumerate() {
+            let element = &mut counts[pair % width];
+            *element = element.checked_add(count).with_context(overflow)?;
+        }
+        let counts = self.elements.iter().copied().zip(counts);
+        Ok(Histogram {
+            counts: counts.filter(|(_, c)| !c.is_zero()).collect(),
+        })
+    }
+}
+
+impl<C> Histogram<C> {
+    #[cfg(test)]
+    pub fn get(&self, element: u8) -> Option<&C> {
+        self.counts
+            .iter()
+            .find(|(e, _)| *e == element)
+            .map(|(_, c)| c)
+    }
+}
+
+impl<C: Ord + Clone + Sub<Output = C>> Histogram<C> {
+    // Count of the most common element minus that of the least common
+    pub fn spread(&self) -> Option<C> {
+        let counts = self.counts.iter().map(|(_, c)| c);
+        let (least, most) = (counts.clone().min()?, counts.max()?);
+        Some(most.clone() - least.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num::BigUint;
+
+    use super::*;
+
+    fn example() -> Polymer {
+        let rules = [
+            "CHB", "HHN", "CBH", "NHC", "HBC", "HCB", "HNC", "NNC", "BHH", "NCB", "NBB", "BNB",
+            "BBN", "BCB", "CCN", "CNC",
+        ];
+        let rules: Vec<_> = rules
+            .iter()
+            .map(|r| ([r.as_bytes()[0], r.as_bytes()[1]], r.as_bytes()[2]))
+            .collect();
+        Polymer::new(b"NNCB", &rules).unwrap()
+    }
+
+    // the polymer itself, step by step
+    fn grown(steps: usize) -> Vec<u8> {
+        let polymer = example();
+        let inserted: HashMap<_, _> = polymer
+            .produces
+            .iter()
+            .enumerate()
+            .filter(|(_, into)| into.len() == 2)
+            .map(|(pair, into)| (pair, into[0] % polymer.elements.len()))
+            .collect();
+        let mut chain = polymer.template.clone();
+        let n = polymer.elements.len();
+        for _ in 0..steps {
+            let mut next = vec![chain[0]];
+            for pair in chain.windows(2) {
+                if let Some(&c) = inserted.get(&(pair[0] * n + pair[1])) {
+                    next.push(c);
+                }
+                next.push(pair[1]);
+            }
+            chain = next;
+        }
+        chain.iter().map(|&e| polymer.elements[e]).collect()
+    }
+
+    #[test]
+    fn matches_growing_the_polymer() {
+        let polymer = example();
+        for steps in 0..8 {
+            let histogram = polymer.histogram::<u64>(steps).unwrap();
+            let chain = grown(steps as usize);
+            for &(element, count) in &histogram.counts {
+                let expected = chain.iter().filter(|&&e| e == element).count() as u64;
+                assert_eq!(count, expected, "{} after {}", element as char, steps);
+            }
+        }
+        assert_eq!(grown(2), b"NBCCNBBBCBHCB");
+    }
+
+    #[test]
+    fn histograms() {
+        let polymer = example();
+        let ten = polymer.histogram::<u64>(10).unwrap();
+        assert_eq!(
+            ten.counts,
+            [(b'B', 1749), (b'C', 298), (b'H', 161), (b'N', 865)]
+        );
+        assert_eq!(ten.spread(), Some(1588));
+        let forty = polymer.histogram::<u64>(40).unwrap();
+        assert_eq!(forty.spread(), Some(2188189693529));
+        assert_eq!(forty.get(b'B'), Some(&2192039569602));
+
+        // the polymer doubles every step, 3 * 2^100 + 1 elements don't fit in u64
+        assert!(polymer.histogram::<u64>(100).is_err());
+        let big = polymer.histogram::<BigUint>(100).unwrap();
+        let total: BigUint = big.counts.iter().map(|(_, c)| c).sum();
+        assert_eq!(total, BigUint::from(3u8) * (BigUint::one() << 100) + 1u8);
+
+        // pairs without a rule stay as they are, however long it runs
+        let stable = Polymer::new(b"ABA", &[(*b"BB", b'C')]).unwrap();
+        let histogram = stable.histogram::<u64>(1_000_000_000).unwrap();
+        assert_eq!(histogram.counts, [(b'A', 2), (b'B', 1)]);
+        assert!(Polymer::new(b"AB", &[(*b"AB", b'C'), (*b"AB", b'D')]).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::{Result, ensure};
+use num::{BigUint, CheckedAdd, CheckedMul, One, Zero};
+
+use crate::matrix::Matrix;
+
+// Fish that spawn every `cycle` days, counting their timers down to 0. A
+// newborn waits `newborn_delay` days more than that before its first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Species {
+    cycle: usize,
+    newborn_delay: usize,
+}
+
+impl Species {
+    pub const LANTERNFISH: Species = Species {
+        cycle: 7,
+        newborn_delay: 2,
+    };
+
+    pub fn new(cycle: usize, newborn_delay: usize) -> Result<Self> {
+        ensure!(cycle > 0, "Fish can't spawn more than once a day");
+        Ok(Species {
+            cycle,
+            newborn_delay,
+        })
+    }
+
+    // Timer values go from 0 to a newborn's
+    pub fn timers(&self) -> usize {
+        self.cycle + self.newborn_delay
+    }
+
+    // Fish by timer
+    pub fn census(&self, timers: &[usize]) -> Result<Vec<u64>> {
+        let mut census = vec![0; self.timers()];
+        for &timer in timers {
+            ensure!(
+                timer < census.len(),
+                "A timer of {} is past a newborn's",
+                timer
+            );
+            census[timer] += 1;
+        }
+        Ok(census)
+    }
+
+    fn check(&self, census: &[impl Sized]) -> Result<()> {
+        ensure!(
+            census.len() == self.timers(),
+            "The census has the wrong timers"
+        );
+        Ok(())
+    }
+
+    // Where every timer goes in a day
+    fn day<C>(&self) -> Option<Matrix<C>>
+    where
+        C: Zero + One + Clone + CheckedAdd + CheckedMul,
+    {
+        let mut matrix = Matrix::zeros(self.timers());
+        for timer in 1..self.timers() {
+            matrix.add_one(timer, timer - 1)?;
+        }
+        matrix.add_one(0, self.cycle - 1)?;
+        matrix.add_one(0, self.timers() - 1)?;
+        Some(matrix)
+    }
+
+    // The census, as census gives it, `days` later. None if it isn't this
+    // species' census or the counts don't fit in C.
+    pub fn after<C>(&self, census: &[C], days: u64) -> Option<Vec<C>>
+    where
+        C: Zero + One + Clone + CheckedAdd + CheckedMul,
+    {
+        self.check(census).ok()?;
+        self.day()?.times_power(census, days)
+    }
+
+    // How many fish there are after `days`, in u64 while that's enough
+    pub fn size_after(&self, census: &[u64], days: u64) -> Result<BigUint> {
+        self.check(census)?;
+        let sum = |counts: Vec<u64>| counts.iter().try_fold(0u64, |s, &c| s.checked_add(c));
+        if let Some(size) = self.after(census, days).and_then(sum) {
+            return Ok(size.into());
+        }
+        let census: Vec<_> = census.iter().map(|&c| BigUint::from(c)).collect();
+        let census = self.after(&census, days).unwrap();
+        Ok(census.into_iter().sum())
+    }
+
+    // The population every day from the census' up to `days` later
+    pub fn series(&self, census: &[u64], days: usize) -> Result<Vec<BigUint>> {
+        self.check(census)?;
+        let day: Matrix<BigUint> = self.day().unwrap();
+        let mut census: Vec<_> = census.iter().map(|&c| BigUint::from(c)).collect();
+        let mut series = vec![census.iter().sum()];
+        for _ in 0..days {
+            census = day.times(&census).unwrap();
+            series.push(census.iter().sum());
+        }
+        Ok(series)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lanternfish() {
+        let fish = Species::LANTERNFISH;
+        let census = fish.census(&[3, 4, 3, 1, 2]).unwrap();
+        assert_eq!(fish.after(&census, 18).unwrap().iter().sum::<u64>(), 26);
+        assert_eq!(fish.size_after(&census, 80).unwrap(), 5934u32.into());
+        assert_eq!(
+            fish.size_after(&census, 256).unwrap(),
+            26984457539u64.into()
+        );
+        // 18 days on, the example lists 6,0,6,4,5,6,0,1,1,2,6,0,1,1,1,2,2,3,3,4,6,7,8,8,8,8
+        assert_eq!(
+            fish.after(&census, 18).unwrap(),
+            [3, 5, 3, 2, 2, 1, 5, 1, 4]
+        );
+
+        let series = fish.series(&census, 18).unwrap();
+        assert_eq!(series.len(), 19);
+        assert_eq!(series[0], 5u32.into());
+        assert_eq!(series[18], 26u32.into());
+        assert!(series.windows(2).all(|w| w[0] <= w[1]));
+
+        // past u64, it carries on in BigUint
+        assert!(fish.after(&census, 2000).is_none());
+        let big = fish.size_after(&census, 2000).unwrap();
+        assert!(big.bits() > 64);
+        assert_eq!(big, *fish.series(&census, 2000).unwrap().last().unwrap());
+        assert!(fish.census(&[9]).is_err());
+    }
+
+    #[test]
+    fn other_species() {
+        // spawning every day with no delay doubles the population
+        let doubling = Species::new(1, 0).unwrap();
+        let census = doubling.census(&[0, 0, 0]).unwrap();
+        assert_eq!(
+            doubling.size_after(&census, 10).unwrap(),
+            (3u32 << 10).into()
+        );
+        assert_eq!(
+            doubling.size_after(&census, 100).unwrap(),
+            BigUint::from(3u32) << 100
+        );
+
+        let slow = Species::new(3, 1).unwrap();
+        assert_eq!(slow.timers(), 4);
+        let census = slow.census(&[0]).unwrap();
+        // 0 -> 2,3 -> 1,2 -> 0,1 -> 2,0,3 -> 1,2,2,3
+        assert_eq!(slow.after(&census, 5).unwrap(), [0, 1, 2, 1]);
+        assert!(Species::new(0, 3).is_err());
+
+        // another species' census doesn't fit
+        let census = Species::LANTERNFISH.census(&[0]).unwrap();
+        assert_eq!(slow.after(&census, 5), None);
+        assert!(slow.size_after(&census, 5).is_err());
+        assert!(slow.series(&census, 5).is_err());
+    }
+}
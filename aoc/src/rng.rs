@@ -0,0 +1,17 @@
+// xorshift, plenty for picking test inputs without pulling in a crate
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    // a state of 0 would stay 0
+    pub fn new(seed: u64) -> Self {
+        Rng(seed | 1)
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+}
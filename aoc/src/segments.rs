@@ -0,0 +1,263 @@
+use anyhow::{Result, bail, ensure};
+use itertools::Itertools;
+
+// Segment bits of the usual seven-segment layout
+pub const TOP: u32 = 1 << 0;
+pub const LEFT_TOP: u32 = 1 << 1;
+pub const RIGHT_TOP: u32 = 1 << 2;
+pub const MID: u32 = 1 << 3;
+pub const LEFT_BOT: u32 = 1 << 4;
+pub const RIGHT_BOT: u32 = 1 << 5;
+pub const BOT: u32 = 1 << 6;
+
+pub const DIGITS: [u32; 10] = [
+    TOP | LEFT_TOP | RIGHT_TOP | LEFT_BOT | RIGHT_BOT | BOT,
+    RIGHT_TOP | RIGHT_BOT,
+    TOP | RIGHT_TOP | MID | LEFT_BOT | BOT,
+    TOP | RIGHT_TOP | MID | RIGHT_BOT | BOT,
+    LEFT_TOP | RIGHT_TOP | MID | RIGHT_BOT,
+    TOP | LEFT_TOP | MID | RIGHT_BOT | BOT,
+    TOP | LEFT_TOP | MID | LEFT_BOT | RIGHT_BOT | BOT,
+    TOP | RIGHT_TOP | RIGHT_BOT,
+    TOP | LEFT_TOP | RIGHT_TOP | MID | LEFT_BOT | RIGHT_BOT | BOT,
+    TOP | LEFT_TOP | RIGHT_TOP | MID | RIGHT_BOT | BOT,
+];
+
+// The segments lit for every symbol, symbols being indices into the table
+#[derive(Clone, Debug)]
+pub struct Glyphs {
+    masks: Vec<u32>,
+    segments: u32,
+}
+
+// Which segment every wire drives, by wire
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Wiring {
+    segments: Vec<u32>,
+}
+
+// The segments every wire could still drive, as masks
+type Domains = Vec<u32>;
+
+impl Glyphs {
+    pub fn new(masks: &[u32]) -> Result<Self> {
+        for (i, mask) in masks.iter().enumerate() {
+            ensure!(
+                !masks[..i].contains(mask),
+                "Symbols {} and {} look the same",
+                masks[..i].iter().position(|m| m == mask).unwrap(),
+                i
+            );
+        }
+        let used = masks.iter().fold(0, |all, mask| all | mask);
+        ensure!(used != 0, "No symbol lights any segment");
+        Ok(Glyphs {
+            masks: masks.to_vec(),
+            segments: u32::BITS - used.leading_zeros(),
+        })
+    }
+
+    pub fn digits() -> Self {
+        Glyphs::new(&DIGITS).unwrap()
+    }
+
+    fn all(&self) -> u32 {
+        u32::MAX >> (u32::BITS - self.segments)
+    }
+
+    pub fn symbol(&self, mask: u32) -> Option<usize> {
+        self.masks.iter().position(|&m| m == mask)
+    }
+
+    // The symbol lighting `size` segments if no other one does as many
+    pub fn by_size(&self, size: u32) -> Option<usize> {
+        let mut sized = self.masks.iter().positions(|m| m.count_ones() == size);
+        match (sized.next(), sized.next()) {
+            (Some(symbol), None) => Some(symbol),
+            _ => None,
+        }
+    }
+
+    // The wirings under which every observation, a mask of live wires, shows
+    // one of the symbols. Stops after `limit` of them.
+    pub fn solve(&self, observations: &[u32], limit: usize) -> Vec<Wiring> {
+        let mut solutions = Vec::new();
+        if observations.iter().all(|&o| o & !self.all() == 0) {
+            let domains = vec![self.all(); self.segments as usize];
+            self.search(domains, observations, limit, &mut solutions);
+        }
+        solutions
+    }
+
+    // The only wiring that fits, an error if there's none or several
+    pub fn solve_unique(&self, observations: &[u32]) -> Result<Wiring> {
+        let mut solutions = self.solve(observations, 2);
+        match solutions.len() {
+            0 => bail!("No wiring shows every pattern as a symbol"),
+            1 => Ok(solutions.pop().unwrap()),
+            _ => bail!("More than one wiring fits the patterns"),
+        }
+    }
+
+    pub fn decode(&self, wiring: &Wiring, wires: u32) -> Option<usize> {
+        self.symbol(wiring.map(wires))
+    }
+
+    fn search(
+        &self,
+        mut domains: Domains,
+        observations: &[u32],
+        limit: usize,
+        found: &mut Vec<Wiring>,
+    ) {
+        if found.len() >= limit || !self.propagate(&mut domains, observations) {
+            return;
+        }
+        let open = domains
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.count_ones() > 1)
+            .min_by_key(|(_, d)| d.count_ones());
+        let Some((wire, &domain)) = open else {
+            let wiring = Wiring {
+                segments: domains.iter().map(|d| d.trailing_zeros()).collect(),
+            };
+            if observations
+                .iter()
+                .all(|&o| self.symbol(wiring.map(o)).is_some())
+            {
+                found.push(wiring);
+            }
+            return;
+        };
+        for segment in (0..self.segments).filter(|s| domain & (1 << s) != 0) {
+            let mut guess = domains.clone();
+            guess[wire] = 1 << segment;
+            self.search(guess, observations, limit, found);
+        }
+    }
+
+    // Narrows the domains down to what every observation still allows,
+    // false once a wire has nowhere left to go
+    fn propagate(&self, domains: &mut Domains, observations: &[u32]) -> bool {
+        let all = self.all();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &observation in observations {
+                // the symbols the observation could still be
+                let candidates = self.masks.iter().filter(|&&glyph| {
+                    glyph.count_ones() == observation.count_ones()
+                        && domains.iter().enumerate().all(|(wire, &domain)| {
+                            let lit = observation & (1 << wire) != 0;
+                            domain & if lit { glyph } else { !glyph & all } != 0
+                        })
+                });
+                let (lit, unlit) = candidates.fold((0, 0), |(lit, unlit), glyph| {
+                    (lit | glyph, unlit | (!glyph & all))
+                });
+                for (wire, domain) in domains.iter_mut().enumerate() {
+                    let allowed = match observation & (1 << wire) != 0 {
+                        true => lit,
+                        false => unlit,
+                    };
+                    changed |= *domain & !allowed != 0;
+                    *domain &= allowed;
+                }
+            }
+            // a segment settled on one wire can't be driven by another
+            for wire in 0..domains.len() {
+                let domain = domains[wire];
+                if domain.count_ones() != 1 {
+                    continue;
+                }
+                for (other, other_domain) in domains.iter_mut().enumerate() {
+                    if other != wire && *other_domain & domain != 0 {
+                        *other_domain &= !domain;
+                        changed = true;
+                    }
+                }
+            }
+            if domains.contains(&0) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Wiring {
+    // The segments lit by the given live wires
+    pub fn map(&self, wires: u32) -> u32 {
+        self.segments
+            .iter()
+            .enumerate()
+            .filter(|(wire, _)| wires & (1 << wire) != 0)
+            .fold(0, |mask, (_, segment)| mask | (1 << segment))
+    }
+}
+
+// Wires named from 'a' on, as a mask
+pub fn wires(pattern: &[u8]) -> Result<u32> {
+    pattern.iter().try_fold(0, |mask, &name| {
+        ensure!(name.is_ascii_lowercase(), "Invalid wire {:?}", name as char);
+        Ok(mask | 1 << (name - b'a'))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn observe(patterns: &str) -> Vec<u32> {
+        patterns
+            .split(' ')
+            .map(|p| wires(p.as_bytes()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn decodes_digits() {
+        let glyphs = Glyphs::digits();
+        let observations = observe("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab");
+        let wiring = glyphs.solve_unique(&observations).unwrap();
+        let shown: Vec<_> = observe("cdfeb fcadb cdfeb cdbaf")
+            .into_iter()
+            .map(|o| glyphs.decode(&wiring, o).unwrap())
+            .collect();
+        assert_eq!(shown, [5, 3, 5, 3]);
+        assert_eq!(glyphs.by_size(3), Some(7));
+        assert_eq!(glyphs.by_size(5), None);
+
+        // every scramble of the ten digits comes back out
+        for permutation in (0..7).permutations(7).step_by(7) {
+            let scrambled: Vec<_> = DIGITS
+                .iter()
+                .map(|&digit| {
+                    let lit = (0..7).filter(|&w| digit & (1 << permutation[w]) != 0);
+                    lit.fold(0, |mask, w| mask | 1 << w)
+                })
+                .collect();
+            let wiring = glyphs.solve_unique(&scrambled).unwrap();
+            assert_eq!(wiring.segments, permutation);
+        }
+    }
+
+    #[test]
+    fn reports_ambiguity() {
+        let glyphs = Glyphs::digits();
+        // a one says nothing of the other five segments
+        assert_eq!(glyphs.solve(&observe("ab"), usize::MAX).len(), 240);
+        assert!(glyphs.solve_unique(&observe("ab")).is_err());
+        // two different ones can't both be shown
+        assert!(glyphs.solve(&observe("ab cd"), 1).is_empty());
+        assert!(glyphs.solve(&observe("abcdefgh"), 1).is_empty());
+
+        // any table works, here of three bars growing from the left
+        let bars = Glyphs::new(&[0b001, 0b011, 0b111]).unwrap();
+        let wiring = bars.solve_unique(&observe("b bc")).unwrap();
+        assert_eq!(bars.decode(&wiring, wires(b"abc").unwrap()), Some(2));
+        assert_eq!(bars.decode(&wiring, wires(b"c").unwrap()), None);
+        assert!(Glyphs::new(&[0b01, 0b10, 0b01]).is_err());
+    }
+}
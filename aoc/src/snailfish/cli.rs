@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail, ensure};
+
+use super::{Number, boxed, largest_pair_magnitude, parse_numbers, sum};
+
+const USAGE: &str = "\
+usage: aoc snailfish [numbers] [options]
+
+Adds up a list of snailfish numbers, one per line, this year's day 18
+input unless a file is given.
+
+options:
+  --trace           print every explode and split while adding the list up
+  --bench <runs>    time the arena numbers against the boxed tree on both parts";
+
+fn print_trace(numbers: &[Number]) {
+    let Some((first, rest)) = numbers.split_first() else {
+        return;
+    };
+    let mut total = first.clone();
+    for number in rest {
+        println!("  {}\n+ {}", total, number);
+        total = total.join(number);
+        for (step, after) in total.reduce_traced() {
+            println!("after {}: {}", step, after);
+        }
+        println!("= {}\n", total);
+    }
+}
+
+// Runs both parts `runs` times, returning how long it took and the answers
+fn time_parts<T>(runs: usize, input: &T, parts: [fn(&T) -> u32; 2]) -> (Duration, [u32; 2])
+where
+    T: ?Sized,
+{
+    let start = Instant::now();
+    let mut answers = [0; 2];
+    for _ in 0..runs {
+        answers = parts.map(|part| part(input));
+    }
+    (start.elapsed(), answers)
+}
+
+fn benchmark(file: &[u8], runs: usize) -> Result<()> {
+    ensure!(runs > 0, "The benchmark needs at least one run");
+    let numbers = parse_numbers(file)?;
+    let elements = boxed::parse_elements(file)?;
+    let sum_magnitude = |numbers: &[Number]| sum(numbers).map_or(0, |n| n.magnitude());
+    let (arena, got) = time_parts(runs, &numbers[..], [sum_magnitude, largest_pair_magnitude]);
+    let (tree, expected) = time_parts(
+        runs,
+        &elements[..],
+        [boxed::sum_magnitude, boxed::largest_pair_magnitude],
+    );
+    if got != expected {
+        bail!(
+            "Arena answers {:?} differ from the boxed {:?}",
+            got,
+            expected
+        );
+    }
+    let per_run = |d: Duration| d.as_secs_f64() * 1e3 / runs as f64;
+    println!("answers: {} {}", got[0], got[1]);
+    println!("boxed: {:?} ({:.2} ms/run)", tree, per_run(tree));
+    println!("arena: {:?} ({:.2} ms/run)", arena, per_run(arena));
+    println!("speedup: {:.2}x", tree.as_secs_f64() / arena.as_secs_f64());
+    Ok(())
+}
+
+// Entry point of the `aoc snailfish` subcommand
+pub fn run(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let (mut trace, mut bench) = (false, None);
+    let mut args = args.iter().map(String::as_str);
+    while let Some(arg) = args.next() {
+        match arg {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "--trace" => trace = true,
+            "--bench" => {
+                let runs = args.next().context("--bench needs a number of runs")?;
+                bench = Some(runs.parse().context("Invalid number of runs")?);
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!("Unexpected argument {:?}\n\n{}", arg, USAGE),
+        }
+    }
+    let file = match path {
+        Some(path) => std::fs::read(path).with_context(|| format!("Failed reading {}", path))?,
+        None => include_bytes!("../../../input/2021/18/input.txt").to_vec(),
+    };
+    if let Some(runs) = bench {
+        return benchmark(&file, runs);
+    }
+
+    let numbers = parse_numbers(&file)?;
+    if trace {
+        print_trace(&numbers);
+    }
+    let total = sum(&numbers).context("No numbers to add")?;
+    println!("sum: {}", total);
+    println!("magnitude: {}", total.magnitude());
+    println!("largest pair: {}", largest_pair_magnitude(&numbers));
+    Ok(())
+}
@@ -0,0 +1,328 @@
+use std::{
+    fmt::Display,
+    ops::{Add, AddAssign},
+};
+
+use anyhow::Result;
+use itertools::Itertools;
+use nom::{
+    IResult,
+    bytes::complete::tag,
+    error::{Error, ErrorKind},
+};
+
+use crate::parse::{Diagnose, lines, parse_unsigned, whole_input};
+
+pub mod boxed;
+pub mod cli;
+
+const EXPLODE_DEPTH: u8 = 4;
+const SPLIT_VALUE: u32 = 10;
+
+// A regular number and how many pairs it's nested in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Cell {
+    value: u32,
+    depth: u8,
+}
+
+// The regular numbers of a snailfish number from left to right, the pairs
+// follow from their depths
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Number {
+    cells: Vec<Cell>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Explode { left: u32, right: u32 },
+    Split(u32),
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Explode { left, right } => write!(f, "explode [{},{}]", left, right),
+            Step::Split(value) => write!(f, "split {}", value),
+        }
+    }
+}
+
+impl Number {
+    // Both numbers as a pair, without reducing it
+    pub fn join(&self, rhs: &Number) -> Number {
+        let mut cells = Vec::with_capacity(self.cells.len() + rhs.cells.len());
+        cells.extend_from_slice(&self.cells);
+        cells.extend_from_slice(&rhs.cells);
+        cells.iter_mut().for_each(|c| c.depth += 1);
+        Number { cells }
+    }
+
+    // Applies the first action that reduces the number, if there is one
+    pub fn reduce_step(&mut self) -> Option<Step> {
+        self.explode().or_else(|| self.split())
+    }
+
+    pub fn reduce(&mut self) {
+        while self.reduce_step().is_some() {}
+    }
+
+    // Every step of the reduction with the number right after it
+    pub fn reduce_traced(&mut self) -> Vec<(Step, Number)> {
+        std::iter::from_fn(|| self.reduce_step().map(|step| (step, self.clone()))).collect()
+    }
+
+    fn explode(&mut self) -> Option<Step> {
+        let i = self.deep_pair()?;
+        let (left, right) = (self.cells[i], self.cells[i + 1]);
+        if i > 0 {
+            self.cells[i - 1].value += left.value;
+        }
+        if let Some(next) = self.cells.get_mut(i + 2) {
+            next.value += right.value;
+        }
+        self.cells[i] = Cell {
+            value: 0,
+            depth: left.depth - 1,
+        };
+        self.cells.remove(i + 1);
+        Some(Step::Explode {
+            left: left.value,
+            right: right.value,
+        })
+    }
+
+    // Where the leftmost pair of two regular numbers nested too deep starts.
+    // In a sum of reduced numbers that's the first cell that deep, but a
+    // number that isn't reduced can have pairs in the way.
+    fn deep_pair(&self) -> Option<usize> {
+        // the way down to the current cell, true for going right
+        let mut sides: Vec<bool> = Vec::new();
+        for (i, cell) in self.cells.iter().enumerate() {
+            // up past the right halves we're done with, then the next one over
+            if i > 0 {
+                while sides.pop() == Some(true) {}
+                sides.push(true);
+            }
+            sides.resize(cell.depth as usize, false);
+            let next = self.cells.get(i + 1);
+            if cell.depth > EXPLODE_DEPTH
+                && sides.last() == Some(&false)
+                && next.is_some_and(|next| next.depth == cell.depth)
+            {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    fn split(&mut self) -> Option<Step> {
+        let i = self.cells.iter().position(|c| c.value >= SPLIT_VALUE)?;
+        let Cell { value, depth } = self.cells[i];
+        self.cells[i] = Cell {
+            value: value / 2,
+            depth: depth + 1,
+        };
+        let right = Cell {
+            value: value.div_ceil(2),
+            depth: depth + 1,
+        };
+        self.cells.insert(i + 1, right);
+        Some(Step::Split(value))
+    }
+
+    pub fn magnitude(&self) -> u32 {
+        // folds every pair as soon as both its halves are on the stack
+        let mut stack: Vec<(u32, u8)> = Vec::with_capacity(self.cells.len());
+        for cell in &self.cells {
+            let (mut value, mut depth) = (cell.value, cell.depth);
+            while let Some(&(left, left_depth)) = stack.last() {
+                if left_depth != depth || depth == 0 {
+                    break;
+                }
+                stack.pop();
+                value = 3 * left + 2 * value;
+                depth -= 1;
+            }
+            stack.push((value, depth));
+        }
+        stack.first().map_or(0, |&(value, _)| value)
+    }
+
+    // Writes the element starting at cells[*next] nested in `depth` pairs
+    fn write_element(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        next: &mut usize,
+        depth: u8,
+    ) -> std::fmt::Result {
+        let cell = self.cells[*next];
+        if cell.depth == depth {
+            *next += 1;
+            return write!(f, "{}", cell.value);
+        }
+        write!(f, "[")?;
+        self.write_element(f, next, depth + 1)?;
+        write!(f, ",")?;
+        self.write_element(f, next, depth + 1)?;
+        write!(f, "]")
+    }
+}
+
+impl Add<&Number> for &Number {
+    type Output = Number;
+
+    fn add(self, rhs: &Number) -> Number {
+        let mut sum = self.join(rhs);
+        sum.reduce();
+        sum
+    }
+}
+
+impl AddAssign<&Number> for Number {
+    fn add_assign(&mut self, rhs: &Number) {
+        self.cells.extend_from_slice(&rhs.cells);
+        self.cells.iter_mut().for_each(|c| c.depth += 1);
+        self.reduce();
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_element(f, &mut 0, 0)
+    }
+}
+
+// Adds the numbers up in order, None for an empty list
+pub fn sum<'a>(numbers: impl IntoIterator<Item = &'a Number>) -> Option<Number> {
+    let mut numbers = numbers.into_iter();
+    let mut total = numbers.next()?.clone();
+    for number in numbers {
+        total += number;
+    }
+    Some(total)
+}
+
+// The largest magnitude from adding two different numbers of the list
+pub fn largest_pair_magnitude(numbers: &[Number]) -> u32 {
+    numbers
+        .iter()
+        .permutations(2)
+        .map(|pair| (pair[0] + pair[1]).magnitude())
+        .max()
+        .unwrap_or(0)
+}
+
+pub fn parse_number(input: &[u8]) -> IResult<&[u8], Number> {
+    fn element<'a>(input: &'a [u8], depth: u8, cells: &mut Vec<Cell>) -> IResult<&'a [u8], ()> {
+        if let Ok((rest, value)) = parse_unsigned(input) {
+            cells.push(Cell { value, depth });
+            return Ok((rest, ()));
+        }
+        let (rest, _) = tag(b"[")(input)?;
+        let depth = depth
+            .checked_add(1)
+            .ok_or_else(|| nom::Err::Failure(Error::new(input, ErrorKind::TooLarge)))?;
+        let (rest, _) = element(rest, depth, cells)?;
+        let (rest, _) = tag(b",")(rest)?;
+        let (rest, _) = element(rest, depth, cells)?;
+        let (rest, _) = tag(b"]")(rest)?;
+        Ok((rest, ()))
+    }
+
+    let mut cells = Vec::new();
+    let (rest, ()) = element(input, 0, &mut cells)?;
+    Ok((rest, Number { cells }))
+}
+
+// One number per line
+pub fn parse_numbers(file: &[u8]) -> Result<Vec<Number>> {
+    whole_input(lines(parse_number))(file).diagnose(file)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn number(text: &str) -> Number {
+        parse_numbers(text.as_bytes()).unwrap().remove(0)
+    }
+
+    #[test]
+    fn round_trip() {
+        for text in [
+            "7",
+            "[1,2]",
+            "[[1,9],[8,5]]",
+            "[[[[1,2],[3,4]],[[5,6],[7,8]]],9]",
+        ] {
+            assert_eq!(number(text).to_string(), text);
+        }
+        assert!(parse_numbers(b"[1,2]\n[3]").is_err());
+        assert!(parse_numbers(b"[1,[2,3]").is_err());
+    }
+
+    #[test]
+    fn reduction_steps() {
+        let mut joined = number("[[[[4,3],4],4],[7,[[8,4],9]]]").join(&number("[1,1]"));
+        let trace: Vec<_> = joined
+            .reduce_traced()
+            .iter()
+            .map(|(step, n)| format!("{}: {}", step, n))
+            .collect();
+        assert_eq!(
+            trace,
+            [
+                "explode [4,3]: [[[[0,7],4],[7,[[8,4],9]]],[1,1]]",
+                "explode [8,4]: [[[[0,7],4],[15,[0,13]]],[1,1]]",
+                "split 15: [[[[0,7],4],[[7,8],[0,13]]],[1,1]]",
+                "split 13: [[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]",
+                "explode [6,7]: [[[[0,7],4],[[7,8],[6,0]]],[8,1]]",
+            ]
+        );
+        assert_eq!(joined.reduce_step(), None);
+
+        // 1 is nested deep enough but it's [2,3] that's a pair of regulars
+        let mut unreduced = number("[[[[[1,[2,3]],4],5],6],7]");
+        let steps: Vec<_> = std::iter::from_fn(|| unreduced.reduce_step()).collect();
+        assert_eq!(
+            steps,
+            [
+                Step::Explode { left: 2, right: 3 },
+                Step::Explode { left: 3, right: 0 }
+            ]
+        );
+        assert_eq!(unreduced.to_string(), "[[[[0,7],5],6],7]");
+    }
+
+    #[test]
+    fn sums_and_magnitudes() {
+        let list = concat!(
+            "[[[0,[4,5]],[0,0]],[[[4,5],[2,6]],[9,5]]]\n",
+            "[7,[[[3,7],[4,3]],[[6,3],[8,8]]]]\n",
+            "[[2,[[0,8],[3,4]]],[[[6,7],1],[7,[1,6]]]]\n",
+            "[[[[2,4],7],[6,[0,5]]],[[[6,8],[2,8]],[[2,1],[4,5]]]]\n",
+            "[7,[5,[[3,8],[1,4]]]]\n",
+            "[[2,[2,2]],[8,[8,1]]]\n",
+            "[2,9]\n",
+            "[1,[[[9,3],9],[[9,0],[0,7]]]]\n",
+            "[[[5,[7,4]],7],1]\n",
+            "[[[[4,2],2],6],[8,7]]\n",
+        );
+        let numbers = parse_numbers(list.as_bytes()).unwrap();
+        let total = sum(&numbers).unwrap();
+        assert_eq!(
+            total.to_string(),
+            "[[[[8,7],[7,7]],[[8,6],[7,7]]],[[[0,7],[6,6]],[8,7]]]"
+        );
+        assert_eq!(total.magnitude(), 3488);
+        assert_eq!(number("[[1,2],[[3,4],5]]").magnitude(), 143);
+        assert_eq!(number("9").magnitude(), 9);
+        assert_eq!(sum(&[]), None);
+
+        let boxed = boxed::parse_elements(list.as_bytes()).unwrap();
+        assert_eq!(boxed::sum_magnitude(&boxed), total.magnitude());
+        assert_eq!(largest_pair_magnitude(&numbers), 3805);
+        assert_eq!(boxed::largest_pair_magnitude(&boxed), 3805);
+    }
+}
@@ -0,0 +1,197 @@
+use std::ops::RangeInclusive;
+
+use anyhow::{Result, bail, ensure};
+
+// A probe launched from the origin moves by its velocity every step, then
+// drag pulls its x velocity a step towards 0 and gravity its y velocity
+// down one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    pub x: RangeInclusive<i32>,
+    pub y: RangeInclusive<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Launch {
+    pub velocity: (i32, i32),
+    // the top of the arc, the launch height if it never climbs
+    pub peak: i32,
+    // the first step that ends in the target
+    pub step: u32,
+}
+
+// The steps after which one coordinate is within the target
+#[derive(Clone, Debug, Default)]
+struct Steps {
+    listed: Vec<u32>,
+    // and every step from this one on, once the probe stops moving that way
+    from: Option<u32>,
+}
+
+impl Steps {
+    fn contains(&self, step: u32) -> bool {
+        self.from.is_some_and(|from| step >= from) || self.listed.binary_search(&step).is_ok()
+    }
+}
+
+// The velocities worth trying to reach `range`, given launches outside it
+// fly past on the first step
+fn x_velocities(range: &RangeInclusive<i32>) -> RangeInclusive<i32> {
+    (*range.start()).min(0)..=(*range.end()).max(0)
+}
+
+fn x_steps(velocity: i32, range: &RangeInclusive<i32>) -> Steps {
+    let mut steps = Steps::default();
+    let (mut x, mut speed) = (0, velocity);
+    for step in 1.. {
+        if speed == 0 {
+            steps.from = range.contains(&x).then_some(step);
+            break;
+        }
+        x += speed;
+        speed -= speed.signum();
+        if range.contains(&x) {
+            steps.listed.push(step);
+        }
+    }
+    steps
+}
+
+fn y_steps(velocity: i32, range: &RangeInclusive<i32>) -> Vec<u32> {
+    let mut steps = Vec::new();
+    let (mut y, mut speed) = (0, velocity);
+    for step in 1.. {
+        y += speed;
+        speed -= 1;
+        if range.contains(&y) {
+            steps.push(step);
+        }
+        // falling below the target, it's never coming back up
+        if speed < 0 && y < *range.start() {
+            break;
+        }
+    }
+    steps
+}
+
+impl Target {
+    pub fn new(x: RangeInclusive<i32>, y: RangeInclusive<i32>) -> Result<Self> {
+        ensure!(!x.is_empty() && !y.is_empty(), "The target area is empty");
+        Ok(Target { x, y })
+    }
+
+    // Every launch that ends a step in the target, by velocity. Each axis
+    // gives the steps it's in range at for each of its velocities, and
+    // launches are the pairs whose steps meet.
+    pub fn launches(&self) -> Result<Vec<Launch>> {
+        let xs: Vec<_> = x_velocities(&self.x)
+            .map(|velocity| (velocity, x_steps(velocity, &self.x)))
+            .collect();
+        let (&low, &high) = (self.y.start(), self.y.end());
+        let fastest = if high < 0 {
+            // coming back down, it passes 0 going one faster than it left
+            -low - 1
+        } else if low > 0 {
+            // anything faster overshoots on the way up and again coming down
+            high
+        } else {
+            // it's back at 0 eventually whatever the velocity, so drifting
+            // sideways has to run out of target first
+            if xs.iter().any(|(_, steps)| steps.from.is_some()) {
+                bail!(
+                    "Infinitely many launches reach a target the probe can fall through straight down"
+                );
+            }
+            let last = xs.iter().flat_map(|(_, steps)| steps.listed.last());
+            high + last.max().copied().unwrap_or(0) as i32
+        };
+
+        let mut launches = Vec::new();
+        for (vx, x_steps) in &xs {
+            for vy in low.min(1)..=fastest {
+                let y_steps = y_steps(vy, &self.y);
+                if let Some(&step) = y_steps.iter().find(|&&step| x_steps.contains(step)) {
+                    launches.push(Launch {
+                        velocity: (*vx, vy),
+                        peak: vy.max(0) * (vy.max(0) + 1) / 2,
+                        step,
+                    });
+                }
+            }
+        }
+        Ok(launches)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // the probe step by step, long enough to finish any arc in the tests
+    fn simulate(target: &Target, velocity: (i32, i32), max_steps: u32) -> Option<(i32, u32)> {
+        let ((mut x, mut y), (mut vx, mut vy)) = ((0, 0), velocity);
+        let (mut peak, mut hit) = (0, None);
+        for step in 1..=max_steps {
+            x += vx;
+            y += vy;
+            vx -= vx.signum();
+            vy -= 1;
+            peak = peak.max(y);
+            if hit.is_none() && target.x.contains(&x) && target.y.contains(&y) {
+                hit = Some(step);
+            }
+        }
+        hit.map(|step| (peak, step))
+    }
+
+    fn brute_force(target: &Target, reach: i32) -> Vec<Launch> {
+        let mut launches = Vec::new();
+        for vx in -reach..=reach {
+            for vy in -reach..=reach {
+                if let Some((peak, step)) = simulate(target, (vx, vy), 4 * reach as u32) {
+                    launches.push(Launch {
+                        velocity: (vx, vy),
+                        peak,
+                        step,
+                    });
+                }
+            }
+        }
+        launches
+    }
+
+    #[test]
+    fn example() {
+        let target = Target::new(20..=30, -10..=-5).unwrap();
+        let launches = target.launches().unwrap();
+        assert_eq!(launches.len(), 112);
+        assert_eq!(launches.iter().map(|l| l.peak).max(), Some(45));
+        assert!(launches.contains(&Launch {
+            velocity: (7, 2),
+            peak: 3,
+            step: 7
+        }));
+    }
+
+    #[test]
+    fn any_quadrant() {
+        for (x, y) in [
+            (20..=30, -10..=-5),
+            (-30..=-20, -10..=-5),
+            (5..=9, 3..=8),
+            (-9..=-4, 6..=10),
+            (-3..=4, -7..=-2),
+            (-14..=-11, -3..=5),
+            (7..=9, -2..=2),
+            (0..=0, 2..=4),
+        ] {
+            let target = Target::new(x.clone(), y.clone()).unwrap();
+            let mut launches = target.launches().unwrap();
+            launches.sort_unstable_by_key(|l| l.velocity);
+            assert_eq!(launches, brute_force(&target, 40), "{:?} {:?}", x, y);
+        }
+        // sitting still at x = 6 and falling through y = 0 from any height
+        assert!(Target::new(5..=8, -2..=2).unwrap().launches().is_err());
+        assert!(Target::new(RangeInclusive::new(1, 0), 0..=1).is_err());
+    }
+}
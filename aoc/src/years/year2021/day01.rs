@@ -1,15 +1,12 @@
-use anyhow::{Result, anyhow};
-use nom::{bytes::complete::tag, multi::separated_list1};
+use anyhow::Result;
 
-use crate::parse::parse_unsigned;
+use crate::parse::{Diagnose, lines, parse_unsigned, whole_input};
 
 type ParserOutput = Vec<u32>;
 type SolverInput = [u32];
 
 pub fn parse_input(file: &[u8]) -> Result<ParserOutput> {
-    separated_list1(tag("\n"), parse_unsigned)(file)
-        .map_err(|_| anyhow!("Parser failed"))
-        .map(|t| t.1)
+    whole_input(lines(parse_unsigned))(file).diagnose(file)
 }
 
 pub fn solve_part1(input: &SolverInput) -> u32 {
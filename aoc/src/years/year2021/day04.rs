@@ -1,167 +1,105 @@
-use anyhow::{Result, anyhow, bail};
-use nom::{bytes::complete::tag, multi::separated_list1};
-
-use crate::parse::parse_unsigned;
+use anyhow::{Context, Result, bail};
+use nom::{
+    bytes::complete::tag,
+    character::complete::line_ending,
+    multi::separated_list1,
+    sequence::{pair, separated_pair},
+};
+
+use crate::{
+    bingo::{Board, Game, WinRule, parse_board},
+    parse::{Diagnose, paragraphs, parse_unsigned, whole_input},
+};
 
 pub struct SolverInput {
-    draws: Vec<u8>,
-    boards: Vec<BingoBoard>,
+    draws: Vec<u32>,
+    game: Game,
 }
 
-type Cell = (u8, bool);
-
-#[derive(Clone, Debug)]
-struct BingoBoard {
-    grid: Vec<Cell>,
-    side_len: usize,
-}
-
-impl BingoBoard {
-    // Returns true if a number was marked
-    fn mark_number(&mut self, number: u8) -> bool {
-        for cell in self.grid.iter_mut() {
-            if !cell.1 && cell.0 == number {
-                cell.1 = true;
-                return true;
-            }
-        }
-        false
-    }
-
-    fn sum_unmarked(&self) -> u32 {
-        self.grid
-            .iter()
-            .filter_map(|c| (!c.1).then_some(c.0 as u32))
-            .sum()
-    }
-
-    fn is_bingo(&self) -> bool {
-        self.is_bingo_horizontal() || self.is_bingo_vertical()
-    }
-
-    fn is_bingo_horizontal(&self) -> bool {
-        'line: for y in 0..self.side_len {
-            for x in 0..self.side_len {
-                if !self[[x, y]].1 {
-                    continue 'line;
-                }
+const USAGE: &str = "\
+usage: aoc bingo [game] [options]
+
+Plays a bingo game, this year's day 4 input unless a file is given, and
+prints every board that wins in the order they do.
+
+options:
+  --rules <list>  comma separated ways to win: rows, columns, diagonals
+                  or card (default rows,columns)";
+
+// Entry point of the `aoc bingo` subcommand
+pub fn run_game(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut rules = vec![WinRule::Rows, WinRule::Columns];
+    let mut args = args.iter().map(String::as_str);
+    while let Some(arg) = args.next() {
+        match arg {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
             }
-            return true;
-        }
-        false
-    }
-
-    fn is_bingo_vertical(&self) -> bool {
-        'line: for x in 0..self.side_len {
-            for y in 0..self.side_len {
-                if !self[[x, y]].1 {
-                    continue 'line;
-                }
+            "--rules" => {
+                let list = args.next().context("--rules needs a list")?;
+                rules = list
+                    .split(',')
+                    .map(|rule| {
+                        Ok(match rule {
+                            "rows" => WinRule::Rows,
+                            "columns" => WinRule::Columns,
+                            "diagonals" => WinRule::Diagonals,
+                            "card" => WinRule::FullCard,
+                            _ => bail!("Unknown rule {:?}\n\n{}", rule, USAGE),
+                        })
+                    })
+                    .collect::<Result<_>>()?;
             }
-            return true;
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!("Unexpected argument {:?}\n\n{}", arg, USAGE),
         }
-        false
     }
-}
-
-impl std::ops::Index<[usize; 2]> for BingoBoard {
-    type Output = Cell;
-
-    fn index(&self, idx: [usize; 2]) -> &Self::Output {
-        &self.grid[idx[0] + self.side_len * idx[1]]
-    }
-}
-
-impl std::ops::IndexMut<[usize; 2]> for BingoBoard {
-    fn index_mut(&mut self, idx: [usize; 2]) -> &mut Self::Output {
-        &mut self.grid[idx[0] + self.side_len * idx[1]]
+    let file = match path {
+        Some(path) => std::fs::read(path).with_context(|| format!("Failed reading {}", path))?,
+        None => include_bytes!("../../../../input/2021/04/input.txt").to_vec(),
+    };
+    let input = parse_game(&file, &rules)?;
+    for win in input.game.play(&input.draws) {
+        let (rows, columns) = input.game.boards()[win.board].shape();
+        println!(
+            "board {} ({}x{}) wins on draw {} with {}, scoring {}",
+            win.board,
+            rows,
+            columns,
+            win.draw,
+            win.number,
+            win.score()
+        );
     }
+    Ok(())
 }
 
 pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
-    let (rest, draws) = separated_list1(tag(b","), parse_unsigned)(file)
-        .map_err(|_| anyhow!("Failed parsing the drawn numbers list"))?;
-
-    let mut boards = vec![];
-    let mut cells = vec![];
-    let mut board_len = None;
-    let mut current_num = None;
-    let mut restidx = 0;
-    while restidx < rest.len() && rest[restidx] == b'\n' {
-        restidx += 1;
-    }
-    while restidx < rest.len() {
-        match rest[restidx] {
-            n @ b'0'..=b'9' => {
-                let n = n - b'0';
-                if current_num.is_none() {
-                    current_num = Some(n);
-                } else {
-                    current_num = Some(current_num.unwrap() * 10 + n);
-                }
-            }
-            b' ' => {
-                if current_num.is_some() {
-                    cells.push((current_num.take().unwrap(), false));
-                }
-            }
-            b'\n' => {
-                if current_num.is_none() {
-                    // entire board done
-                    boards.push(BingoBoard {
-                        grid: cells,
-                        side_len: board_len.unwrap(),
-                    });
-                    cells = vec![];
-                } else {
-                    // a line done
-                    cells.push((current_num.take().unwrap(), false));
-                    if board_len.is_none() {
-                        // a (first) line done, set the length, it's the same for every board
-                        board_len = Some(cells.len());
-                    }
-                }
-            }
-            c => bail!("Unrecognized character when parsing boards ({})", c as char),
-        }
-        restidx += 1;
-    }
-
-    if !cells.is_empty() {
-        boards.push(BingoBoard {
-            grid: cells,
-            side_len: board_len.unwrap(),
-        });
-    }
+    parse_game(file, &[WinRule::Rows, WinRule::Columns])
+}
 
-    Ok(SolverInput { draws, boards })
+fn parse_game(file: &[u8], rules: &[WinRule]) -> Result<SolverInput> {
+    let (draws, boards) = whole_input(separated_pair(
+        separated_list1(tag(b","), parse_unsigned),
+        pair(line_ending, line_ending),
+        paragraphs(parse_board),
+    ))(file)
+    .diagnose(file)?;
+    let boards = boards.into_iter().map(Board::new).collect::<Result<_>>()?;
+    let game = Game::new(boards, rules)?;
+    Ok(SolverInput { draws, game })
 }
 
 pub fn solve_part1(input: &SolverInput) -> u32 {
-    let mut boards = input.boards.clone();
-    for number in input.draws.iter() {
-        for board in boards.iter_mut() {
-            if board.mark_number(*number) && board.is_bingo() {
-                return board.sum_unmarked() * *number as u32;
-            }
-        }
-    }
-    0
+    let wins = input.game.play(&input.draws);
+    wins.first().map_or(0, |win| win.score())
 }
 
 pub fn solve_part2(input: &SolverInput) -> u32 {
-    let mut boards = input.boards.clone();
-    for number in input.draws.iter() {
-        boards.iter_mut().for_each(|board| {
-            board.mark_number(*number);
-        });
-        if boards.len() == 1 && boards[0].is_bingo() {
-            return boards[0].sum_unmarked() * *number as u32;
-        } else {
-            boards.retain(|board| !board.is_bingo());
-        }
-    }
-    0
+    let wins = input.game.play(&input.draws);
+    wins.last().map_or(0, |win| win.score())
 }
 
 #[cfg(test)]
@@ -218,18 +156,23 @@ mod test {
                 14, 21, 17, 24, 4, 10, 16, 15, 9, 19, 18, 8, 23, 26, 20, 22, 11, 13, 6, 5, 2, 0,
                 12, 3, 7,
             ],
-        ]
-        .map(|board| board.map(|num| (num, false)));
-        assert_eq!(parsed.boards.len(), desired_boards.len());
-        for i in 0..parsed.boards.len() {
-            assert_eq!(
-                parsed.boards[i].grid, desired_boards[i],
-                "Board #{} parsed improperly",
-                i
-            );
-            assert_eq!(parsed.boards[i].side_len, 5);
+        ];
+        let boards = parsed.game.boards();
+        assert_eq!(boards.len(), desired_boards.len());
+        for (i, (board, desired)) in boards.iter().zip(desired_boards).enumerate() {
+            assert_eq!(board.numbers(), desired, "Board #{} parsed improperly", i);
+            assert_eq!(board.shape(), (5, 5));
         }
     }
 
+    #[test]
+    fn win_order() {
+        let parsed = rules::parse_expect!(EXAMPLE, "example");
+        let wins = parsed.game.play(&parsed.draws);
+        let order: Vec<_> = wins.iter().map(|w| (w.board, w.number)).collect();
+        assert_eq!(order, [(2, 24), (0, 16), (1, 13)]);
+        assert_eq!(wins[0].unmarked_sum, 188);
+    }
+
     rules::make_test_for_day!(example, EXAMPLE, 4512, 1924);
 }
@@ -1,51 +1,87 @@
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, bail};
 use nom::{bytes::complete::tag, multi::separated_list1};
 
-use crate::parse::parse_unsigned;
+use crate::{
+    parse::{Diagnose, parse_unsigned, whole_input},
+    population::Species,
+};
 
-type SolverInput = [u32; TOTAL_CATEGORIES];
+// lanternfish by timer
+type SolverInput = Vec<u64>;
 
-const CYCLE_LENGTH: usize = 7;
-const NEW_CYCLE_EXTRA: usize = 2;
-const TOTAL_CATEGORIES: usize = CYCLE_LENGTH + NEW_CYCLE_EXTRA;
-
-pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
-    let numbers = separated_list1(tag(b","), parse_unsigned::<usize>)(file)
-        .map_err(|_| anyhow!("Failed parsing lines"))?
-        .1;
-    let mut lanternfish = [0; TOTAL_CATEGORIES];
-    for fish in numbers {
-        lanternfish[fish] += 1;
-    }
-    Ok(lanternfish)
+fn parse_timers(file: &[u8]) -> Result<Vec<usize>> {
+    whole_input(separated_list1(tag(b","), parse_unsigned))(file).diagnose(file)
 }
 
-pub fn solve_part1(input: &SolverInput) -> u32 {
-    const DAYS: usize = 80;
-    let mut lanternfish = *input;
-    for _ in 0..DAYS {
-        let expired = lanternfish[0];
-        for i in 0..8 {
-            lanternfish[i] = lanternfish[i + 1];
+const USAGE: &str = "\
+usage: aoc lanternfish [timers] [options]
+
+Prints the population every day as day,fish lines, starting from this year's
+day 6 input unless a file of timers is given.
+
+options:
+  --days <n>     how many days to simulate (default 256)
+  --cycle <n>    days between spawns (default 7)
+  --delay <n>    extra days before a newborn's first spawn (default 2)";
+
+// Entry point of the `aoc lanternfish` subcommand
+pub fn run_series(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let (mut days, mut cycle, mut delay) = (256, 7, 2);
+    let mut args = args.iter().map(String::as_str);
+    while let Some(arg) = args.next() {
+        match arg {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "--days" | "--cycle" | "--delay" => {
+                let n = args
+                    .next()
+                    .with_context(|| format!("{} needs a number", arg))?;
+                let n = n
+                    .parse()
+                    .with_context(|| format!("Invalid {} value", arg))?;
+                match arg {
+                    "--days" => days = n,
+                    "--cycle" => cycle = n,
+                    _ => delay = n,
+                }
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!("Unexpected argument {:?}\n\n{}", arg, USAGE),
         }
-        lanternfish[6] += expired;
-        lanternfish[8] = expired;
     }
-    lanternfish.iter().sum()
+    let file = match path {
+        Some(path) => std::fs::read(path).with_context(|| format!("Failed reading {}", path))?,
+        None => include_bytes!("../../../../input/2021/06/input.txt").to_vec(),
+    };
+    let species = Species::new(cycle, delay)?;
+    let census = species.census(&parse_timers(&file)?)?;
+    for (day, fish) in species.series(&census, days)?.iter().enumerate() {
+        println!("{},{}", day, fish);
+    }
+    Ok(())
+}
+
+pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
+    let census = Species::LANTERNFISH.census(&parse_timers(file)?)?;
+    // the school only grows, so both answers fit if the last one does
+    size_after(&census, 256)?;
+    Ok(census)
+}
+
+fn size_after(census: &[u64], days: u64) -> Result<u64> {
+    let size = Species::LANTERNFISH.size_after(census, days)?;
+    u64::try_from(size).context("There are too many fish to count in a u64")
+}
+
+pub fn solve_part1(input: &SolverInput) -> u64 {
+    size_after(input, 80).expect("The size was checked when parsing")
 }
 
 pub fn solve_part2(input: &SolverInput) -> u64 {
-    const DAYS: usize = 256;
-    let mut lanternfish = input.map(|v| v as u64);
-    for _ in 0..DAYS {
-        let expired = lanternfish[0];
-        for i in 0..8 {
-            lanternfish[i] = lanternfish[i + 1];
-        }
-        lanternfish[6] += expired;
-        lanternfish[8] = expired;
-    }
-    lanternfish.iter().sum()
+    size_after(input, 256).expect("The size was checked when parsing")
 }
 
 #[cfg(test)]
@@ -1,15 +1,13 @@
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
 use nom::{bytes::complete::tag, multi::separated_list1};
 
-use crate::parse::parse_unsigned;
+use crate::{
+    alignment::{Cost, align},
+    parse::parse_unsigned,
+};
 
-type ParserOutput = Vec<u32>;
-type SolverInput = [u32];
-
-// u32::abs_diff is nightly :(
-fn abs_diff(l: u32, r: u32) -> u32 {
-    l.abs_diff(r)
-}
+type ParserOutput = Vec<u64>;
+type SolverInput = [u64];
 
 pub fn parse_input(file: &[u8]) -> Result<ParserOutput> {
     let mut numbers = separated_list1(tag(b","), parse_unsigned)(file)
@@ -19,37 +17,66 @@ pub fn parse_input(file: &[u8]) -> Result<ParserOutput> {
     Ok(numbers)
 }
 
-pub fn solve_part1(input: &SolverInput) -> u32 {
-    let median = if input.len() % 2 == 1 {
-        input[(input.len() / 2) + 1]
-    } else {
-        let left = input[(input.len() / 2) - 1];
-        let right = input[input.len() / 2];
-        (right + left) / 2
-    };
-    input.iter().map(|p| abs_diff(*p, median)).sum()
-}
+const USAGE: &str = "\
+usage: aoc crabs [positions] [options]
+
+Prints where the crabs should line up and the fuel it takes, starting from
+this year's day 7 input unless a file of positions is given.
 
-pub fn solve_part2(input: &SolverInput) -> u32 {
-    let fuel_sum = |to| {
-        let mut total_sum = 0;
-        for pos in input {
-            // S = ((a0 + an)*n) / 2
-            let difference = abs_diff(*pos, to);
-            let n = difference + 1;
-            let sum = (difference * n) / 2;
-            total_sum += sum;
+options:
+  --cost <name>   linear, triangular or quadratic (default linear)
+  --power <k>     moving d costs d to the k-th power instead";
+
+// Entry point of the `aoc crabs` subcommand
+pub fn run_alignment(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut cost = Cost::Linear;
+    let mut args = args.iter().map(String::as_str);
+    while let Some(arg) = args.next() {
+        match arg {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "--cost" => {
+                cost = match args.next() {
+                    Some("linear") => Cost::Linear,
+                    Some("triangular") => Cost::Triangular,
+                    Some("quadratic") => Cost::Quadratic,
+                    other => bail!("Unknown cost {:?}\n\n{}", other, USAGE),
+                }
+            }
+            "--power" => {
+                let k: u32 = args
+                    .next()
+                    .context("--power needs a number")?
+                    .parse()
+                    .context("Invalid --power value")?;
+                cost = Cost::custom(move |d| d.saturating_pow(k));
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!("Unexpected argument {:?}\n\n{}", arg, USAGE),
         }
-        total_sum
+    }
+    let file = match path {
+        Some(path) => std::fs::read(path).with_context(|| format!("Failed reading {}", path))?,
+        None => include_bytes!("../../../../input/2021/07/input.txt").to_vec(),
     };
+    let positions = parse_input(&file)?;
+    let alignment = align(&positions, &cost).context("There are no crabs")?;
+    println!(
+        "line up at {} for {} fuel",
+        alignment.position, alignment.fuel
+    );
+    Ok(())
+}
 
-    let average = {
-        let sum: u32 = input.iter().sum();
-        let n = input.len() as u32;
-        sum / n
-    };
+pub fn solve_part1(input: &SolverInput) -> u64 {
+    align(input, &Cost::Linear).map_or(0, |a| a.fuel)
+}
 
-    u32::min(fuel_sum(average), fuel_sum(average + 1))
+pub fn solve_part2(input: &SolverInput) -> u64 {
+    align(input, &Cost::Triangular).map_or(0, |a| a.fuel)
 }
 
 #[cfg(test)]
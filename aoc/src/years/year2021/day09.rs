@@ -2,15 +2,15 @@ use std::collections::{HashMap, VecDeque};
 
 use anyhow::Result;
 use itertools::Itertools;
-use nom::{bytes::complete::tag, character::complete::digit1, multi::separated_list1};
+use nom::character::complete::digit1;
+
+use crate::parse::{Diagnose, lines, whole_input};
 
 type ParserOutput<'a> = Vec<&'a [u8]>;
 type SolverInput<'a> = [&'a [u8]];
 
 pub fn parse_input(file: &[u8]) -> Result<ParserOutput<'_>> {
-    separated_list1::<_, _, _, nom::error::Error<_>, _, _>(tag(b"\n"), digit1)(file)
-        .map_err(|_| anyhow::anyhow!("Failed parsing lines"))
-        .map(|t| t.1)
+    whole_input(lines(digit1))(file).diagnose(file)
 }
 
 pub fn solve_part1(input: &SolverInput) -> u32 {
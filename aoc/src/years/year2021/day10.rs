@@ -5,98 +5,46 @@ use nom::{
 };
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
-type ParserOutput = Vec<Line>;
-type SolverInput = [Line];
+use crate::brackets::{Delimiters, Report};
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum Line {
-    Incomplete(Vec<u8>),
-    Corrupted(u32),
-}
-
-fn autocomplete_score(to_close: &[u8]) -> u64 {
-    let mut score = 0;
-    for bracket in to_close.iter().rev() {
-        score *= 5;
-        score += match *bracket {
-            b'(' => 1,
-            b'[' => 2,
-            b'{' => 3,
-            b'<' => 4,
-            _ => unreachable!(),
-        }
-    }
-    score
-}
+type ParserOutput = Vec<Report>;
+type SolverInput = [Report];
 
 pub fn parse_input(file: &[u8]) -> anyhow::Result<ParserOutput> {
-    fn parse_line(line: &[u8]) -> Line {
-        let mut stack = Vec::new();
-        for character in line {
-            match character {
-                b'(' | b'[' | b'{' | b'<' => stack.push(*character),
-                b')' => {
-                    if stack.pop().unwrap() != b'(' {
-                        return Line::Corrupted(3);
-                    }
-                }
-                b']' => {
-                    if stack.pop().unwrap() != b'[' {
-                        return Line::Corrupted(57);
-                    }
-                }
-                b'}' => {
-                    if stack.pop().unwrap() != b'{' {
-                        return Line::Corrupted(1197);
-                    }
-                }
-                b'>' => {
-                    if stack.pop().unwrap() != b'<' {
-                        return Line::Corrupted(25137);
-                    }
-                }
-                _ => unreachable!(),
-            }
-        }
-        Line::Incomplete(stack)
-    }
-
+    let chunks = Delimiters::chunks();
     separated_list1::<_, _, _, Error<_>, _, _>(tag(b"\n"), is_a("([{<>}])"))(file)
         .map_err(|_| anyhow::anyhow!("Failed parsing braces"))
-        .map(|t| t.1.into_par_iter().map(parse_line).collect::<Vec<_>>())
+        .map(|t| t.1.into_par_iter().map(|line| chunks.check(line)).collect())
 }
 
-pub fn solve_part1(input: &SolverInput) -> u32 {
+pub fn solve_part1(input: &SolverInput) -> u64 {
+    let chunks = Delimiters::chunks();
     input
         .par_iter()
-        .map(|line| {
-            if let Line::Corrupted(err_val) = line {
-                *err_val
-            } else {
-                0
-            }
-        })
+        .filter_map(|report| report.first_error())
+        .map(|error| chunks.corruption_score(error))
         .sum()
 }
 
 pub fn solve_part2(input: &SolverInput) -> u64 {
-    let mut scores: Vec<_> = input
+    let chunks = Delimiters::chunks();
+    let scores: Option<Vec<_>> = input
         .par_iter()
-        .filter_map(|line| {
-            if let Line::Incomplete(to_close) = line {
-                Some(autocomplete_score(to_close))
-            } else {
-                None
-            }
-        })
+        .filter(|report| report.is_incomplete())
+        .map(|report| chunks.completion_score(&report.completion))
         .collect();
+    let Some(mut scores) = scores else {
+        return 0;
+    };
     scores.sort_unstable();
-    scores[scores.len() / 2] // scores are always odd in number
+    // scores are always odd in number
+    scores.get(scores.len() / 2).copied().unwrap_or(0)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::brackets::SyntaxError;
 
     const EXAMPLE: &[u8] = concat!(
         "[({(<(())[]>[[{[]{<()<>>\n",
@@ -116,16 +64,29 @@ mod test {
     fn parse_example() {
         let parsed = rules::parse_expect!(EXAMPLE, "example");
 
-        assert_eq!(parsed[2], Line::Corrupted(1197));
-        assert_eq!(parsed[4], Line::Corrupted(3));
-        assert_eq!(parsed[5], Line::Corrupted(57));
-        assert_eq!(parsed[7], Line::Corrupted(3));
-        assert_eq!(parsed[8], Line::Corrupted(25137));
-        for i in 0..parsed.len() {
-            if let Ok(_) = [2, 4, 5, 7, 8].binary_search(&i) {
+        assert_eq!(
+            parsed[2].first_error(),
+            Some(&SyntaxError::Corrupted {
+                position: 12,
+                expected: b']',
+                found: b'}'
+            })
+        );
+        let expected_found = |idx: usize| match parsed[idx].first_error() {
+            Some(&SyntaxError::Corrupted {
+                expected, found, ..
+            }) => (expected, found),
+            _ => panic!("Line {} isn't corrupted", idx),
+        };
+        assert_eq!(expected_found(4), (b']', b')'));
+        assert_eq!(expected_found(5), (b')', b']'));
+        assert_eq!(expected_found(7), (b'>', b')'));
+        assert_eq!(expected_found(8), (b']', b'>'));
+        for (i, line) in parsed.iter().enumerate() {
+            if [2, 4, 5, 7, 8].binary_search(&i).is_ok() {
                 continue;
             }
-            assert!(matches!(parsed[i], Line::Incomplete(_)));
+            assert!(line.is_incomplete());
         }
     }
 
@@ -134,16 +95,24 @@ mod test {
     #[test]
     fn part2_example_scores() {
         let parsed = rules::parse_expect!(EXAMPLE, "example");
+        let chunks = Delimiters::chunks();
 
-        let get_incomplete = |idx| match &parsed[idx] {
-            Line::Incomplete(v) => v,
-            _ => panic!("Trying to extract not from an incomplete line"),
-        };
-
-        assert_eq!(autocomplete_score(&get_incomplete(0)), 288957);
-        assert_eq!(autocomplete_score(&get_incomplete(1)), 5566);
-        assert_eq!(autocomplete_score(&get_incomplete(3)), 1480781);
-        assert_eq!(autocomplete_score(&get_incomplete(6)), 995444);
-        assert_eq!(autocomplete_score(&get_incomplete(9)), 294);
+        for (idx, completion, score) in [
+            (0, "}}]])})]", 288957),
+            (1, ")}>]})", 5566),
+            (3, "}}>}>))))", 1480781),
+            (6, "]]}}]}]}>", 995444),
+            (9, "])}>", 294),
+        ] {
+            assert_eq!(parsed[idx].completion, completion.as_bytes());
+            assert_eq!(
+                chunks.completion_score(&parsed[idx].completion),
+                Some(score)
+            );
+        }
+        // a line opening with a closer is neither corrupted nor incomplete
+        let stray = parse_input(b")[]").unwrap();
+        assert!(!stray[0].is_incomplete());
+        assert_eq!(solve_part1(&stray), 0);
     }
 }
@@ -1,102 +1,63 @@
-use std::collections::HashSet;
-
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Result, anyhow};
 use ndarray::Array2;
-use nom::{
-    bytes::complete::tag, character::complete::digit1, error::Error, multi::separated_list1,
+use nom::error::Error;
+
+use crate::{
+    automaton::{
+        Automaton, MOORE, Phase, Rule,
+        dense::{Dense, Edges},
+    },
+    parse::grid,
 };
 
 type SolverInput = Array2<u8>;
 
-const SIDE_LEN: usize = 10;
+const FLASH_ENERGY: u8 = 10;
 
-fn step(grid: &mut SolverInput) -> u32 {
-    let (rows, cols) = grid.dim();
-    let mut flashes = 0;
-    let mut to_flash = HashSet::new();
-    grid.indexed_iter_mut().for_each(|((y, x), v)| {
-        *v += 1;
-        if *v > 9 {
-            to_flash.insert([y, x]);
-        }
+type Octopuses = Automaton<u8, Dense<u8>>;
+
+// Every octopus gains energy, then the ones past 9 flash and give a level to
+// their neighbours until nothing flashes anymore. Flashed ones sit at 0 and
+// take no energy for the rest of the step.
+fn octopuses(grid: &SolverInput) -> Octopuses {
+    let flash = Rule::new(&MOORE, |energy, neighbours| match energy {
+        0 => 0,
+        e if e >= FLASH_ENERGY => 0,
+        e => e + neighbours.iter().filter(|&&n| n >= FLASH_ENERGY).count() as u8,
     });
-    let propagate_flash = |g: &mut SolverInput, hs: &mut HashSet<_>, y, x| {
-        // 0 flashed already and they keep their value
-        let cell = &mut g[[y, x]];
-        if *cell != 0 {
-            *cell += 1;
-            if *cell == 10 {
-                hs.insert([y, x]);
-            }
-        }
-    };
-    while let Some(&coords) = to_flash.iter().next() {
-        to_flash.remove(&coords);
-        let [y, x] = coords;
-        flashes += 1;
-        grid[[y, x]] = 0;
-        let top_ok = y > 0;
-        let bottom_ok = y < cols - 1;
-        let left_ok = x > 0;
-        let right_ok = x < rows - 1;
-        if left_ok {
-            propagate_flash(grid, &mut to_flash, y, x - 1);
-            if top_ok {
-                propagate_flash(grid, &mut to_flash, y - 1, x - 1);
-            }
-            if bottom_ok {
-                propagate_flash(grid, &mut to_flash, y + 1, x - 1);
-            }
-        }
-        if right_ok {
-            propagate_flash(grid, &mut to_flash, y, x + 1);
-            if top_ok {
-                propagate_flash(grid, &mut to_flash, y - 1, x + 1);
-            }
-            if bottom_ok {
-                propagate_flash(grid, &mut to_flash, y + 1, x + 1);
-            }
-        }
-        if top_ok {
-            propagate_flash(grid, &mut to_flash, y - 1, x);
-        }
-        if bottom_ok {
-            propagate_flash(grid, &mut to_flash, y + 1, x);
-        }
-    }
-    flashes
+    let phases = vec![Phase::Once(Rule::map(|e| e + 1)), Phase::UntilStable(flash)];
+    Automaton::new(Dense::new(grid.clone(), Edges::Background(0)), phases)
 }
 
-pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
-    let digits = separated_list1::<_, _, _, Error<_>, _, _>(tag(b"\n"), digit1)(file)
-        .map_err(|_| anyhow!("Failed parsing digits"))?
-        .1;
-
-    let (rows, cols) = (digits.len(), digits[0].len());
+// Flashes of the last step
+fn flashes(octopuses: &Octopuses) -> u32 {
+    octopuses.space.cells().iter().filter(|&&e| e == 0).count() as u32
+}
 
-    Array2::from_shape_vec(
-        (rows, cols),
-        digits.into_iter().flatten().map(|c| *c - b'0').collect(),
-    )
-    .context("Failed creating the octopus array")
+pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
+    grid::<_, Error<_>, _>(|c| c.is_ascii_digit().then(|| c - b'0'))(file)
+        .map_err(|_| anyhow!("Failed parsing the octopus grid"))
+        .map(|t| t.1)
 }
 
 pub fn solve_part1(input: &SolverInput) -> u32 {
-    let mut grid = input.clone();
-    let mut flashes = 0;
-    for _ in 0..100 {
-        flashes += step(&mut grid);
-    }
-    flashes
+    let mut octopuses = octopuses(input);
+    (0..100)
+        .map(|_| {
+            octopuses.step().expect("Flashes run out on a finite grid");
+            flashes(&octopuses)
+        })
+        .sum()
 }
 
 pub fn solve_part2(input: &SolverInput) -> u32 {
-    let mut grid = input.clone();
-    let mut steps = 0;
-    while step(&mut grid) != (SIDE_LEN * SIDE_LEN) as u32 {
-        steps += 1;
+    let mut octopuses = octopuses(input);
+    loop {
+        octopuses.step().expect("Flashes run out on a finite grid");
+        if flashes(&octopuses) as usize == input.len() {
+            return octopuses.steps() as u32;
+        }
     }
-    steps + 1
 }
 
 #[cfg(test)]
@@ -105,6 +66,13 @@ mod test {
 
     use super::*;
 
+    // one step on the grid in place
+    fn step(grid: &mut SolverInput) {
+        let mut octopuses = octopuses(grid);
+        octopuses.step().unwrap();
+        grid.assign(octopuses.space.cells());
+    }
+
     const EXAMPLE_SMALL: &[u8] =
         concat!("11111\n", "19991\n", "19191\n", "19991\n", "11111\n",).as_bytes();
 
@@ -1,183 +1,236 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
-use anyhow::{Context, Result, anyhow};
-use nom::{
-    bytes::complete::tag, character::complete::alpha0, error::Error, multi::separated_list1,
-    sequence::separated_pair,
-};
+use anyhow::{Context, Result, bail, ensure};
+use nom::{bytes::complete::tag, character::complete::alpha1, sequence::separated_pair};
 
-type SolverInput = HashMap<u32, Node>;
+use crate::parse::{Diagnose, lines, whole_input};
 
-const START_ID: u32 = compute_identifier(b"start");
+type SolverInput = CaveSystem;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Cave {
     Start,
     End,
-    Small,
+    // with its bit in the visited masks
+    Small(u32),
     Big,
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
-pub struct Node {
-    kind: Cave,
-    connections: Vec<u32>,
+// The caves by index, with their names interned at parse time
+#[derive(Debug)]
+pub struct CaveSystem {
+    names: Vec<String>,
+    kinds: Vec<Cave>,
+    connections: Vec<Vec<usize>>,
+    start: usize,
+    end: usize,
 }
 
-#[derive(Clone)]
-struct Path<'a> {
-    passed: HashSet<&'a Node>,
-    double_passed_small: Option<&'a Node>,
-    last_identifier: u32,
+// Where a path is, which small caves it went through, which of those it
+// went through twice and how many more it can go through twice
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Walk {
+    cave: usize,
+    visited: u64,
+    doubled: u64,
+    revisits: usize,
 }
 
-const fn compute_identifier(name: &[u8]) -> u32 {
-    let mut value = 0;
-    let mut i = 0;
-    while i < name.len() {
-        value *= (b'z' - b'A') as u32;
-        value += (name[i] - b'A') as u32;
-        i += 1;
+impl Walk {
+    // The walk after moving into `cave`, None if it can't go there
+    fn enter(self, cave: usize, kind: Cave) -> Option<Walk> {
+        let mut next = Walk { cave, ..self };
+        match kind {
+            Cave::Start => return None,
+            Cave::End | Cave::Big => {}
+            Cave::Small(bit) if self.visited & (1 << bit) == 0 => next.visited |= 1 << bit,
+            Cave::Small(bit) if self.revisits > 0 && self.doubled & (1 << bit) == 0 => {
+                next.doubled |= 1 << bit;
+                next.revisits -= 1;
+            }
+            Cave::Small(_) => return None,
+        }
+        // which caves were doubled stops mattering once no more can be
+        if next.revisits == 0 {
+            next.doubled = 0;
+        }
+        Some(next)
     }
-    value
 }
 
-pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
-    let make_empty_node = |name: &[u8]| {
-        Some(match name.len() {
-            3 => Node {
-                kind: Cave::End,
-                connections: vec![],
-            },
-            5 => Node {
-                kind: Cave::Start,
-                connections: vec![],
-            },
-            _ => {
-                if name[0] >= b'A' && name[0] <= b'Z' {
-                    Node {
-                        kind: Cave::Big,
-                        connections: vec![],
-                    }
-                } else if name[0] >= b'a' && name[0] <= b'z' {
-                    Node {
-                        kind: Cave::Small,
-                        connections: vec![],
-                    }
-                } else {
-                    return None;
+impl CaveSystem {
+    pub fn new(connections: &[(&str, &str)]) -> Result<Self> {
+        let mut system = CaveSystem {
+            names: Vec::new(),
+            kinds: Vec::new(),
+            connections: Vec::new(),
+            start: 0,
+            end: 0,
+        };
+        let mut ids = HashMap::new();
+        let mut small_caves = 0;
+        let mut intern = |system: &mut CaveSystem, name: &str| -> Result<usize> {
+            if let Some(&id) = ids.get(name) {
+                return Ok(id);
+            }
+            let kind = match name {
+                "start" => Cave::Start,
+                "end" => Cave::End,
+                _ if name.chars().all(|c| c.is_ascii_uppercase()) => Cave::Big,
+                _ if name.chars().all(|c| c.is_ascii_lowercase()) => {
+                    ensure!(small_caves < u64::BITS, "More than 64 small caves");
+                    small_caves += 1;
+                    Cave::Small(small_caves - 1)
                 }
+                _ => bail!("Cave {:?} is neither big nor small", name),
+            };
+            let id = system.names.len();
+            system.names.push(name.to_owned());
+            system.kinds.push(kind);
+            system.connections.push(Vec::new());
+            ids.insert(name.to_owned(), id);
+            Ok(id)
+        };
+        for &(from, to) in connections {
+            let (from, to) = (intern(&mut system, from)?, intern(&mut system, to)?);
+            if system.kinds[from] == Cave::Big && system.kinds[to] == Cave::Big {
+                bail!(
+                    "Big caves {} and {} are connected, there'd be endless paths",
+                    system.names[from],
+                    system.names[to]
+                );
             }
-        })
-    };
+            system.connections[from].push(to);
+            system.connections[to].push(from);
+        }
+        system.start = *ids.get("start").context("There's no start cave")?;
+        system.end = *ids.get("end").context("There's no end cave")?;
+        Ok(system)
+    }
 
-    let connections = separated_list1::<_, _, _, Error<_>, _, _>(
-        tag(b"\n"),
-        separated_pair(alpha0, tag(b"-"), alpha0),
-    )(file)
-    .map_err(|_| anyhow!("Failed parsing digits"))?
-    .1;
-    let mut nodes = HashMap::new();
-    for conn in connections {
-        let side0 = compute_identifier(conn.0);
-        let side1 = compute_identifier(conn.1);
-        let node0 = make_empty_node(conn.0).context("Invalid side in connection")?;
-        let node1 = make_empty_node(conn.1).context("Invalid side in connection")?;
-        nodes.entry(side0).or_insert(node0).connections.push(side1);
-        nodes.entry(side1).or_insert(node1).connections.push(side0);
+    fn start_walk(&self, revisits: usize) -> Walk {
+        Walk {
+            cave: self.start,
+            visited: 0,
+            doubled: 0,
+            revisits,
+        }
     }
-    Ok(nodes)
-}
 
-pub fn solve_part1(input: &SolverInput) -> u32 {
-    let mut found_paths = 0;
-    let mut paths: Vec<Path> = vec![];
-    {
-        let mut first_path = Path {
-            passed: HashSet::new(),
-            last_identifier: START_ID,
-            double_passed_small: None,
-        };
-        first_path.passed.insert(&input[&START_ID]);
-        paths.push(first_path);
+    fn moves(&self, walk: Walk) -> impl Iterator<Item = Walk> + '_ {
+        self.connections[walk.cave]
+            .iter()
+            .filter_map(move |&cave| walk.enter(cave, self.kinds[cave]))
     }
 
-    while let Some(path) = paths.pop() {
-        for other_id in input[&path.last_identifier].connections.iter() {
-            let other_node = &input[other_id];
-            if other_node.kind == Cave::End {
-                // finished path
-                found_paths += 1;
-                continue;
+    // Paths from start to end through every small cave at most once, except
+    // for `revisits` of them that can be gone through twice
+    pub fn count_paths(&self, revisits: usize) -> u64 {
+        fn count(system: &CaveSystem, walk: Walk, memo: &mut HashMap<Walk, u64>) -> u64 {
+            if walk.cave == system.end {
+                return 1;
             }
-            if other_node.kind == Cave::Start {
-                // can't go back to start
-                continue;
+            if let Some(&paths) = memo.get(&walk) {
+                return paths;
             }
-            if other_node.kind == Cave::Small && path.passed.contains(other_node) {
-                // can't enter a small cave 2 times
+            let moves: Vec<_> = system.moves(walk).collect();
+            let paths = moves
+                .into_iter()
+                .map(|next| count(system, next, memo))
+                .sum();
+            memo.insert(walk, paths);
+            paths
+        }
+        count(self, self.start_walk(revisits), &mut HashMap::new())
+    }
+
+    // The paths count_paths counts, as the names of their caves
+    pub fn paths(&self, revisits: usize) -> Vec<Vec<&str>> {
+        let mut paths = Vec::new();
+        let mut stack = vec![(self.start_walk(revisits), 0)];
+        // the caves that led to the walk being looked at
+        let mut route = Vec::new();
+        while let Some((walk, depth)) = stack.pop() {
+            route.truncate(depth);
+            route.push(walk.cave);
+            if walk.cave == self.end {
+                paths.push(route.iter().map(|&c| self.names[c].as_str()).collect());
                 continue;
             }
-
-            let mut new_path = path.clone();
-            new_path.passed.insert(other_node);
-            new_path.last_identifier = *other_id;
-            paths.push(new_path);
+            stack.extend(self.moves(walk).map(|next| (next, depth + 1)));
         }
+        paths.sort_unstable();
+        paths
     }
-    found_paths
 }
 
-pub fn solve_part2(input: &SolverInput) -> u32 {
-    let mut found_paths = 0;
-    let mut paths: Vec<Path> = vec![];
-    {
-        let mut first_path = Path {
-            passed: HashSet::new(),
-            last_identifier: START_ID,
-            double_passed_small: None,
-        };
-        first_path.passed.insert(&input[&START_ID]);
-        paths.push(first_path);
-    }
+const USAGE: &str = "\
+usage: aoc caves [connections] [options]
 
-    while let Some(path) = paths.pop() {
-        for other_id in input[&path.last_identifier].connections.iter() {
-            let other_node = &input[other_id];
-            if other_node.kind == Cave::End {
-                // finished path
-                found_paths += 1;
-                continue;
-            }
-            if other_node.kind == Cave::Start {
-                // can't go back to start
-                continue;
+Counts the paths through a cave system, this year's day 12 input unless a
+file of connections is given.
+
+options:
+  --revisits <n>    how many small caves a path may go through twice (default 0)
+  --list            print every path, one per line";
+
+// Entry point of the `aoc caves` subcommand
+pub fn run_paths(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let (mut revisits, mut list) = (0, false);
+    let mut args = args.iter().map(String::as_str);
+    while let Some(arg) = args.next() {
+        match arg {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
             }
-            if other_node.kind == Cave::Small && path.passed.contains(other_node) {
-                if path.double_passed_small.is_none() {
-                    // double pass
-                    let mut new_path = path.clone();
-                    new_path.double_passed_small = Some(other_node);
-                    new_path.last_identifier = *other_id;
-                    paths.push(new_path);
-                }
-                // else can't enter a small cave 2 times
-                continue;
+            "--list" => list = true,
+            "--revisits" => {
+                let n = args.next().context("--revisits needs a number")?;
+                revisits = n.parse().context("Invalid number of revisits")?;
             }
-
-            let mut new_path = path.clone();
-            new_path.passed.insert(other_node);
-            new_path.last_identifier = *other_id;
-            paths.push(new_path);
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!("Unexpected argument {:?}\n\n{}", arg, USAGE),
         }
     }
-    found_paths
+    let file = match path {
+        Some(path) => std::fs::read(path).with_context(|| format!("Failed reading {}", path))?,
+        None => include_bytes!("../../../../input/2021/12/input.txt").to_vec(),
+    };
+    let system = parse_input(&file)?;
+    if list {
+        for path in system.paths(revisits) {
+            println!("{}", path.join(","));
+        }
+    }
+    println!("{} paths", system.count_paths(revisits));
+    Ok(())
+}
+
+pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
+    let connection = separated_pair(alpha1, tag(b"-"), alpha1);
+    let connections = whole_input(lines(connection))(file).diagnose(file)?;
+    let connections: Vec<_> = connections
+        .into_iter()
+        .map(|(from, to)| {
+            let name = |n| std::str::from_utf8(n).context("Cave names are ASCII");
+            Ok((name(from)?, name(to)?))
+        })
+        .collect::<Result<_>>()?;
+    CaveSystem::new(&connections)
+}
+
+pub fn solve_part1(input: &SolverInput) -> u64 {
+    input.count_paths(0)
+}
+
+pub fn solve_part2(input: &SolverInput) -> u64 {
+    input.count_paths(1)
 }
 
 #[cfg(test)]
 mod test {
-    use nom::AsBytes;
-
     use super::*;
 
     const EXAMPLE_SMALL: &[u8] = concat!(
@@ -191,58 +244,84 @@ mod test {
     )
     .as_bytes();
 
+    const EXAMPLE_LARGE: &[u8] = concat!(
+        "fs-end\n",
+        "he-DX\n",
+        "fs-he\n",
+        "start-DX\n",
+        "pj-DX\n",
+        "end-zg\n",
+        "zg-sl\n",
+        "zg-pj\n",
+        "pj-he\n",
+        "RW-he\n",
+        "fs-DX\n",
+        "pj-RW\n",
+        "zg-RW\n",
+        "start-pj\n",
+        "he-WI\n",
+        "zg-he\n",
+        "pj-fs\n",
+        "start-RW\n",
+    )
+    .as_bytes();
+
+    fn connected<'a>(system: &'a CaveSystem, name: &str) -> Vec<&'a str> {
+        let id = system.names.iter().position(|n| n == name).unwrap();
+        let mut names: Vec<_> = system.connections[id]
+            .iter()
+            .map(|&c| system.names[c].as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
     #[test]
     fn parse_example_small() {
         let parsed = rules::parse_expect!(EXAMPLE_SMALL, "small example");
-        {
-            let from = parsed
-                .get(&compute_identifier(b"start".as_bytes()))
-                .expect("No paths originating from start");
-            assert!(matches!(from.kind, Cave::Start));
-            assert!(
-                from.connections
-                    .contains(&compute_identifier(b"A".as_bytes()))
-            );
-            assert!(
-                from.connections
-                    .contains(&compute_identifier(b"b".as_bytes()))
-            );
-        }
-        {
-            let from = parsed
-                .get(&compute_identifier(b"end".as_bytes()))
-                .expect("No paths originating from A");
-            assert!(matches!(from.kind, Cave::End));
-            assert!(
-                from.connections
-                    .contains(&compute_identifier(b"A".as_bytes()))
-            );
-            assert!(
-                from.connections
-                    .contains(&compute_identifier(b"b".as_bytes()))
-            );
-        }
-        {
-            let from = parsed
-                .get(&compute_identifier(b"A".as_bytes()))
-                .expect("No paths originating from A");
-            assert!(matches!(from.kind, Cave::Big));
-            assert!(
-                from.connections
-                    .contains(&compute_identifier(b"c".as_bytes()))
-            );
-            assert!(
-                from.connections
-                    .contains(&compute_identifier(b"b".as_bytes()))
-            );
-            assert!(
-                from.connections
-                    .contains(&compute_identifier(b"start".as_bytes()))
-            );
-            assert!(
-                from.connections
-                    .contains(&compute_identifier(b"end".as_bytes()))
+        assert_eq!(parsed.kinds[parsed.start], Cave::Start);
+        assert_eq!(parsed.kinds[parsed.end], Cave::End);
+        assert_eq!(connected(&parsed, "start"), ["A", "b"]);
+        assert_eq!(connected(&parsed, "end"), ["A", "b"]);
+        assert_eq!(connected(&parsed, "A"), ["b", "c", "end", "start"]);
+    }
+
+    #[test]
+    fn names_of_any_length() {
+        // the three and five letter caves are ordinary ones
+        let system = parse_input(b"start-abc\nabc-QWERT\nQWERT-end\nabc-end").unwrap();
+        assert_eq!(system.count_paths(0), 2);
+        assert_eq!(
+            system.paths(0),
+            [
+                vec!["start", "abc", "QWERT", "end"],
+                vec!["start", "abc", "end"]
+            ]
+        );
+        assert!(parse_input(b"start-Ab\nAb-end").is_err());
+        assert!(parse_input(b"start-A\nA-B\nB-end").is_err());
+        assert!(parse_input(b"start-a").is_err());
+    }
+
+    #[test]
+    fn revisit_budgets() {
+        let system = parse_input(EXAMPLE_SMALL).unwrap();
+        assert_eq!(system.count_paths(0), 10);
+        assert_eq!(system.count_paths(1), 36);
+        for revisits in 0..4 {
+            assert_eq!(
+                system.count_paths(revisits),
+                system.paths(revisits).len() as u64
             );
         }
+        assert!(
+            system
+                .paths(1)
+                .contains(&vec!["start", "b", "A", "b", "A", "c", "A", "end"])
+        );
+        // b, c and d can each be visited twice at most
+        assert_eq!(system.count_paths(3), system.count_paths(10));
     }
+
+    rules::make_test_for_day!(example, EXAMPLE_LARGE, 226, 3509);
 }
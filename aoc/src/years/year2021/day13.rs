@@ -4,12 +4,11 @@ use anyhow::{Result, anyhow};
 use itertools::Itertools;
 use nom::{
     bytes::complete::tag,
-    character::complete::one_of,
-    multi::separated_list1,
-    sequence::{preceded, separated_pair},
+    character::complete::{line_ending, one_of},
+    sequence::{pair, preceded, separated_pair},
 };
 
-use crate::parse::parse_unsigned;
+use crate::parse::{lines, parse_unsigned};
 
 type SolverInput = (HashSet<(u32, u32)>, Vec<Fold>);
 
@@ -50,11 +49,12 @@ fn fold_paper(paper: &mut HashSet<(u32, u32)>, fold: Fold) {
 
 pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
     let point_parser = separated_pair(parse_unsigned, tag(b","), parse_unsigned);
-    let points_parser = separated_list1(tag(b"\n"), point_parser);
+    let points_parser = lines(point_parser);
     let axis_parser = separated_pair(one_of("xy"), tag(b"="), parse_unsigned);
     let instruction_parser = preceded(tag(b"fold along "), axis_parser);
-    let instructions_parser = separated_list1(tag("\n"), instruction_parser);
-    let mut input_parser = separated_pair(points_parser, tag(b"\n\n"), instructions_parser);
+    let instructions_parser = lines(instruction_parser);
+    let blank_line = pair(line_ending, line_ending);
+    let mut input_parser = separated_pair(points_parser, blank_line, instructions_parser);
 
     let (points, folds) = input_parser(file)
         .map_err(|_| anyhow!("Failed parsing input"))?
@@ -1,737 +1,558 @@
-use std::cmp::Reverse;
-
-use anyhow::{Result, anyhow};
-use nom::{
-    branch::alt, bytes::streaming::tag, combinator::map_opt, multi::separated_list1,
-    sequence::preceded,
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    io::Write,
+    time::Duration,
 };
+
+use anyhow::{Context, Result, bail, ensure};
+use itertools::Itertools;
+use nom::bytes::complete::is_not;
 use priority_queue::PriorityQueue;
 
-// This is just awful but it works.
+use crate::parse::{Diagnose, lines, whole_input};
 
 type SolverInput = Burrow;
 
-const ROOM_COUNT: usize = 4;
-const ROOM_DEPTH: usize = 2;
-const SIDE_ROOM_DEPTH: usize = 2;
+// Spliced under the top row of the rooms for part 2, room by room
+const UNFOLDED_ROWS: [&[u8]; 2] = [b"DCBA", b"DBAC"];
+
+// What's in every open cell: 0 if empty, amphipod type + 1 otherwise
+type State = Vec<u8>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Place {
+    // counted from the leftmost hallway cell
+    Hallway(usize),
+    // room index and depth, 0 being the top
+    Room(usize, usize),
+}
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
-enum Amphipod {
-    Amber,
-    Bronze,
-    Copper,
-    Desert,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub amphipod: u8,
+    pub from: Place,
+    pub to: Place,
+    pub energy: usize,
 }
 
-#[derive(Clone, Copy)]
-enum BurrowPosition {
-    SideRoomLeft,
-    OverRoom0,
-    HallwayLeft,
-    OverRoom1,
-    HallwayMiddle,
-    OverRoom2,
-    HallwayRight,
-    OverRoom3,
-    SideRoomRight,
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    pub energy: usize,
+    pub moves: Vec<Move>,
+    // from the start, one more than there are moves
+    pub states: Vec<State>,
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+// The burrow as a graph of open cells, parsed from the puzzle's diagram.
+// Room k (from the left) is where amphipods of type k, written as the k-th
+// letter, want to end up.
+#[derive(Clone, Debug)]
 pub struct Burrow {
-    // rooms [0] is the leftmost room, rooms[0][0] is the deepest spot in leftmost room
-    rooms: [Vec<Amphipod>; ROOM_COUNT],
-    room_depth: usize,
-    hallway_left: Option<Amphipod>,
-    hallway_middle: Option<Amphipod>,
-    hallway_right: Option<Amphipod>,
-    // [0] is the shallow spot, [1] is the deep one
-    left_side_room: [Option<Amphipod>; SIDE_ROOM_DEPTH],
-    right_side_room: [Option<Amphipod>; SIDE_ROOM_DEPTH],
+    // the diagram, amphipods included
+    map: Vec<Vec<u8>>,
+    // (row, column) of every open cell
+    cells: Vec<(usize, usize)>,
+    places: Vec<Place>,
+    // hallway cells amphipods may stop on, ie. not right outside a room
+    stops: Vec<usize>,
+    // cells of every room, top first
+    rooms: Vec<Vec<usize>>,
+    // steps between two cells and the cells walked through, destination included
+    paths: Vec<Vec<(usize, u128)>>,
+    // energy per step for every type
+    costs: Vec<usize>,
+    start: State,
 }
 
-impl Amphipod {
-    fn from_ascii(text: &[u8]) -> Option<Amphipod> {
-        match text {
-            b"A" => Some(Amphipod::Amber),
-            b"B" => Some(Amphipod::Bronze),
-            b"C" => Some(Amphipod::Copper),
-            b"D" => Some(Amphipod::Desert),
-            _ => None,
-        }
-    }
+fn letter(amphipod: u8) -> char {
+    (b'A' + amphipod) as char
+}
 
-    fn desired_room_pos(&self) -> BurrowPosition {
+impl Display for Place {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Amphipod::Amber => BurrowPosition::OverRoom0,
-            Amphipod::Bronze => BurrowPosition::OverRoom1,
-            Amphipod::Copper => BurrowPosition::OverRoom2,
-            Amphipod::Desert => BurrowPosition::OverRoom3,
+            Place::Hallway(i) => write!(f, "hallway {}", i),
+            Place::Room(room, depth) => {
+                write!(f, "room {} spot {}", letter(*room as u8), depth + 1)
+            }
         }
     }
+}
 
-    fn desired_room_idx(&self) -> usize {
-        Burrow::room_idx_under_pos(self.desired_room_pos())
-    }
-
-    fn move_cost(&self) -> usize {
-        match self {
-            Amphipod::Amber => 1,
-            Amphipod::Bronze => 10,
-            Amphipod::Copper => 100,
-            Amphipod::Desert => 1000,
-        }
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} from {} to {} ({} energy)",
+            letter(self.amphipod),
+            self.from,
+            self.to,
+            self.energy
+        )
     }
 }
 
-impl BurrowPosition {
-    fn base_moves_to(&self, to: Self) -> usize {
-        match self {
-            Self::SideRoomLeft => match to {
-                Self::SideRoomLeft => panic!("Getting move cost to the same spot"),
-                Self::OverRoom0 => 1,
-                Self::HallwayLeft => 2,
-                Self::OverRoom1 => 3,
-                Self::HallwayMiddle => 4,
-                Self::OverRoom2 => 5,
-                Self::HallwayRight => 6,
-                Self::OverRoom3 => 7,
-                Self::SideRoomRight => 8,
-            },
-            Self::OverRoom0 => match to {
-                Self::SideRoomLeft => to.base_moves_to(*self),
-                Self::OverRoom0 => panic!("Getting move cost to the same spot"),
-                Self::HallwayLeft => 1,
-                Self::OverRoom1 => 2,
-                Self::HallwayMiddle => 3,
-                Self::OverRoom2 => 4,
-                Self::HallwayRight => 5,
-                Self::OverRoom3 => 6,
-                Self::SideRoomRight => 7,
-            },
-            Self::HallwayLeft => match to {
-                Self::SideRoomLeft | Self::OverRoom0 => to.base_moves_to(*self),
-                Self::HallwayLeft => panic!("Getting move cost to the same spot"),
-                Self::OverRoom1 => 1,
-                Self::HallwayMiddle => 2,
-                Self::OverRoom2 => 3,
-                Self::HallwayRight => 4,
-                Self::OverRoom3 => 5,
-                Self::SideRoomRight => 6,
-            },
-            Self::OverRoom1 => match to {
-                Self::SideRoomLeft | Self::OverRoom0 | Self::HallwayLeft => to.base_moves_to(*self),
-                Self::OverRoom1 => panic!("Getting move cost to the same spot"),
-                Self::HallwayMiddle => 1,
-                Self::OverRoom2 => 2,
-                Self::HallwayRight => 3,
-                Self::OverRoom3 => 4,
-                Self::SideRoomRight => 5,
-            },
-            Self::HallwayMiddle => match to {
-                Self::SideRoomLeft | Self::OverRoom0 | Self::HallwayLeft | Self::OverRoom1 => {
-                    to.base_moves_to(*self)
-                }
-                Self::HallwayMiddle => panic!("Getting move cost to the same spot"),
-                Self::OverRoom2 => 1,
-                Self::HallwayRight => 2,
-                Self::OverRoom3 => 3,
-                Self::SideRoomRight => 4,
-            },
-            Self::OverRoom2 => match to {
-                Self::SideRoomLeft
-                | Self::OverRoom0
-                | Self::HallwayLeft
-                | Self::OverRoom1
-                | Self::HallwayMiddle => to.base_moves_to(*self),
-                Self::OverRoom2 => panic!("Getting move cost to the same spot"),
-                Self::HallwayRight => 1,
-                Self::OverRoom3 => 2,
-                Self::SideRoomRight => 3,
-            },
-            Self::HallwayRight => match to {
-                Self::SideRoomLeft
-                | Self::OverRoom0
-                | Self::HallwayLeft
-                | Self::OverRoom1
-                | Self::HallwayMiddle
-                | Self::OverRoom2 => to.base_moves_to(*self),
-                Self::HallwayRight => panic!("Getting move cost to the same spot"),
-                Self::OverRoom3 => 1,
-                Self::SideRoomRight => 2,
-            },
-            Self::OverRoom3 => match to {
-                Self::SideRoomLeft
-                | Self::OverRoom0
-                | Self::HallwayLeft
-                | Self::OverRoom1
-                | Self::HallwayMiddle
-                | Self::OverRoom2
-                | Self::HallwayRight => to.base_moves_to(*self),
-                Self::OverRoom3 => panic!("Getting move cost to the same spot"),
-                Self::SideRoomRight => 1,
-            },
-            Self::SideRoomRight => match to {
-                Self::SideRoomLeft
-                | Self::OverRoom0
-                | Self::HallwayLeft
-                | Self::OverRoom1
-                | Self::HallwayMiddle
-                | Self::OverRoom2
-                | Self::HallwayRight
-                | Self::OverRoom3 => to.base_moves_to(*self),
-                Self::SideRoomRight => panic!("Getting move cost to the same spot"),
-            },
+impl Display for Solution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for mv in self.moves.iter() {
+            writeln!(f, "{}", mv)?;
         }
+        write!(f, "total: {} energy", self.energy)
     }
 }
 
 impl Burrow {
-    fn pos_over_room_idx(idx: usize) -> BurrowPosition {
-        match idx {
-            0 => BurrowPosition::OverRoom0,
-            1 => BurrowPosition::OverRoom1,
-            2 => BurrowPosition::OverRoom2,
-            3 => BurrowPosition::OverRoom3,
-            _ => panic!("Requesting position over room index out of bounds"),
+    pub fn new(map: Vec<Vec<u8>>) -> Result<Self> {
+        let is_open = |row: usize, col: usize| {
+            map.get(row)
+                .and_then(|r| r.get(col))
+                .is_some_and(|&b| b == b'.' || b.is_ascii_uppercase())
+        };
+        let hallway_row = (0..map.len())
+            .find(|&row| (0..map[row].len()).any(|col| is_open(row, col)))
+            .context("The burrow has no open cells")?;
+
+        let mut cells = Vec::new();
+        let mut places = Vec::new();
+        let mut stops = Vec::new();
+        let mut rooms = Vec::new();
+        let hallway_cols: Vec<_> = (0..map[hallway_row].len())
+            .filter(|&col| is_open(hallway_row, col))
+            .collect();
+        for (i, &col) in hallway_cols.iter().enumerate() {
+            cells.push((hallway_row, col));
+            places.push(Place::Hallway(i));
+            if !is_open(hallway_row + 1, col) {
+                stops.push(cells.len() - 1);
+                continue;
+            }
+            let mut room = Vec::new();
+            for row in (hallway_row + 1..map.len()).take_while(|&row| is_open(row, col)) {
+                cells.push((row, col));
+                places.push(Place::Room(rooms.len(), room.len()));
+                room.push(cells.len() - 1);
+            }
+            rooms.push(room);
         }
-    }
-
-    fn room_idx_under_pos(pos: BurrowPosition) -> usize {
-        match pos {
-            BurrowPosition::OverRoom0 => 0,
-            BurrowPosition::OverRoom1 => 1,
-            BurrowPosition::OverRoom2 => 2,
-            BurrowPosition::OverRoom3 => 3,
-            _ => panic!("Requesting room under a position that has no room underneath"),
+        for (row, line) in map.iter().enumerate() {
+            for col in (0..line.len()).filter(|&col| is_open(row, col)) {
+                ensure!(
+                    cells.contains(&(row, col)),
+                    "Open cell at line {}, column {} is neither hallway nor room",
+                    row + 1,
+                    col + 1
+                );
+            }
+        }
+        ensure!(!rooms.is_empty(), "The burrow has no rooms");
+        ensure!(cells.len() <= 128, "The burrow has too many cells");
+
+        let mut start = vec![0; cells.len()];
+        let mut counts = vec![0; rooms.len()];
+        for (i, &(row, col)) in cells.iter().enumerate() {
+            let ch = map[row][col];
+            if ch == b'.' {
+                continue;
+            }
+            let amphipod = ch - b'A';
+            if amphipod as usize >= rooms.len() {
+                bail!("There's no room for amphipod {}", ch as char);
+            }
+            start[i] = amphipod + 1;
+            counts[amphipod as usize] += 1;
+        }
+        for (amphipod, (&count, room)) in counts.iter().zip(rooms.iter()).enumerate() {
+            ensure!(
+                count == room.len(),
+                "There are {} amphipods {} for a room of {}",
+                count,
+                letter(amphipod as u8),
+                room.len()
+            );
         }
+        let costs = (0..rooms.len() as u32)
+            .map(|k| 10usize.checked_pow(k))
+            .collect::<Option<_>>()
+            .context("Too many amphipod types for the default costs")?;
+
+        let paths = (0..cells.len())
+            .map(|from| Self::paths_from(&cells, from))
+            .collect();
+        Ok(Burrow {
+            map,
+            cells,
+            places,
+            stops,
+            rooms,
+            paths,
+            costs,
+            start,
+        })
     }
 
-    fn get_room_under_pos(&self, pos: BurrowPosition) -> &Vec<Amphipod> {
-        let idx = Self::room_idx_under_pos(pos);
-        &self.rooms[idx]
+    // Breadth first over the open cells, which in a burrow form a tree
+    fn paths_from(cells: &[(usize, usize)], from: usize) -> Vec<(usize, u128)> {
+        let index: HashMap<_, _> = cells.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+        let mut paths = vec![None; cells.len()];
+        paths[from] = Some((0, 0));
+        let mut queue = VecDeque::from([from]);
+        while let Some(cell) = queue.pop_front() {
+            let (row, col) = cells[cell];
+            let (steps, walked) = paths[cell].unwrap();
+            let neighbours = [
+                (row.wrapping_sub(1), col),
+                (row + 1, col),
+                (row, col.wrapping_sub(1)),
+                (row, col + 1),
+            ];
+            for next in neighbours.iter().filter_map(|n| index.get(n).copied()) {
+                if paths[next].is_none() {
+                    paths[next] = Some((steps + 1, walked | 1 << next));
+                    queue.push_back(next);
+                }
+            }
+        }
+        // every open cell is connected through the hallway
+        paths.into_iter().map(|p| p.unwrap_or((0, 0))).collect()
     }
 
-    fn get_room_under_pos_mut(&mut self, pos: BurrowPosition) -> &mut Vec<Amphipod> {
-        let idx = match pos {
-            BurrowPosition::OverRoom0 => 0,
-            BurrowPosition::OverRoom1 => 1,
-            BurrowPosition::OverRoom2 => 2,
-            BurrowPosition::OverRoom3 => 3,
-            _ => panic!("Requesting room under a position that has no room underneath"),
-        };
-        &mut self.rooms[idx]
+    // Energy per step for each type, in room order
+    pub fn with_costs(mut self, costs: Vec<usize>) -> Result<Self> {
+        ensure!(
+            costs.len() == self.rooms.len(),
+            "Got {} costs for {} amphipod types",
+            costs.len(),
+            self.rooms.len()
+        );
+        self.costs = costs;
+        Ok(self)
     }
 
-    fn room_needs_popping(&self, room_idx: usize) -> bool {
-        if let Some(pod) = self.rooms[room_idx].last() {
-            if pod.desired_room_idx() != room_idx {
-                true
-            } else {
-                self.rooms[room_idx].iter().any(|p| p != pod)
+    // The same burrow with extra rows of amphipods under the top of the
+    // rooms, every row giving the amphipod for each room in order
+    pub fn unfolded(&self, rows: &[&[u8]]) -> Result<Self> {
+        let top = self.cells[self.rooms[0][0]].0;
+        // the walls of the new rows are those of the bottom one
+        let bottom = self.cells[*self.rooms[0].last().unwrap()].0;
+        let mut map = self.map.clone();
+        for (i, row) in rows.iter().enumerate() {
+            ensure!(
+                row.len() == self.rooms.len(),
+                "An unfolded row needs an amphipod for each of the {} rooms",
+                self.rooms.len()
+            );
+            let mut line = self.map[bottom].clone();
+            for (room, &amphipod) in self.rooms.iter().zip(row.iter()) {
+                line[self.cells[room[0]].1] = amphipod;
             }
-        } else {
-            false
+            map.insert(top + 1 + i, line);
         }
+        Burrow::new(map)?.with_costs(self.costs.clone())
     }
 
-    fn is_solved(&self) -> bool {
-        for (ridx, room) in self.rooms.iter().enumerate() {
-            if room.len() != self.room_depth {
-                return false;
-            }
-            if room.iter().any(|p| p.desired_room_idx() != ridx) {
-                return false;
-            }
+    // The diagram with the amphipods where `state` has them
+    pub fn render(&self, state: &State) -> String {
+        let mut map = self.map.clone();
+        for (&(row, col), &pod) in self.cells.iter().zip(state.iter()) {
+            map[row][col] = match pod {
+                0 => b'.',
+                pod => b'A' + pod - 1,
+            };
         }
-        true
+        map.iter()
+            .map(|row| String::from_utf8_lossy(row))
+            .join("\n")
     }
 
-    fn is_position_occupied(&self, pos: BurrowPosition) -> bool {
-        match pos {
-            BurrowPosition::SideRoomLeft => self.left_side_room.iter().all(|o| o.is_some()),
-            BurrowPosition::HallwayLeft => self.hallway_left.is_some(),
-            BurrowPosition::HallwayMiddle => self.hallway_middle.is_some(),
-            BurrowPosition::HallwayRight => self.hallway_right.is_some(),
-            BurrowPosition::SideRoomRight => self.right_side_room.iter().all(|o| o.is_some()),
-            _ => false,
+    // Every state along the solution, with the move that led to it and the
+    // energy spent so far
+    pub fn replay(&self, solution: &Solution) -> Vec<String> {
+        let mut frames = vec![format!("start\n{}", self.render(&solution.states[0]))];
+        let mut total = 0;
+        let steps = solution.moves.iter().zip(solution.states[1..].iter());
+        for (i, (mv, state)) in steps.enumerate() {
+            total += mv.energy;
+            let header = format!("move {}: {}, {} total", i + 1, mv, total);
+            frames.push(format!("{}\n{}", header, self.render(state)));
         }
+        frames
     }
 
-    fn is_position_unoccupied(&self, pos: BurrowPosition) -> bool {
-        !self.is_position_occupied(pos)
+    fn is_solved(&self, state: &State) -> bool {
+        self.rooms
+            .iter()
+            .enumerate()
+            .all(|(k, room)| room.iter().all(|&c| state[c] == k as u8 + 1))
     }
 
-    fn can_move(&self, from: BurrowPosition, to: BurrowPosition) -> bool {
-        match from {
-            BurrowPosition::SideRoomLeft => match to {
-                BurrowPosition::SideRoomLeft => panic!("Checking move to the same spot"),
-                BurrowPosition::OverRoom0 => true,
-                BurrowPosition::HallwayLeft | BurrowPosition::OverRoom1 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                }
-                BurrowPosition::HallwayMiddle | BurrowPosition::OverRoom2 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                }
-                BurrowPosition::HallwayRight | BurrowPosition::OverRoom3 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::SideRoomRight => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                        && self.is_position_unoccupied(BurrowPosition::SideRoomRight)
-                }
-            },
-            BurrowPosition::OverRoom0 => match to {
-                BurrowPosition::SideRoomLeft => {
-                    self.is_position_unoccupied(BurrowPosition::SideRoomLeft)
-                }
-                BurrowPosition::OverRoom0 => panic!("Checking move to the same spot"),
-                BurrowPosition::HallwayLeft | BurrowPosition::OverRoom1 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                }
-                BurrowPosition::HallwayMiddle | BurrowPosition::OverRoom2 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                }
-                BurrowPosition::HallwayRight | BurrowPosition::OverRoom3 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::SideRoomRight => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                        && self.is_position_unoccupied(BurrowPosition::SideRoomRight)
-                }
-            },
-            BurrowPosition::HallwayLeft => match to {
-                BurrowPosition::SideRoomLeft => {
-                    self.is_position_unoccupied(BurrowPosition::SideRoomLeft)
-                }
-                BurrowPosition::OverRoom0 => true,
-                BurrowPosition::HallwayLeft => panic!("Checking move to the same spot"),
-                BurrowPosition::OverRoom1 => true,
-                BurrowPosition::HallwayMiddle | BurrowPosition::OverRoom2 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                }
-                BurrowPosition::HallwayRight | BurrowPosition::OverRoom3 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::SideRoomRight => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                        && self.is_position_unoccupied(BurrowPosition::SideRoomRight)
-                }
-            },
-            BurrowPosition::OverRoom1 => match to {
-                BurrowPosition::SideRoomLeft => {
-                    self.is_position_unoccupied(BurrowPosition::SideRoomLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                }
-                BurrowPosition::OverRoom0 | BurrowPosition::HallwayLeft => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                }
-                BurrowPosition::OverRoom1 => panic!("Checking move to the same spot"),
-                BurrowPosition::HallwayMiddle | BurrowPosition::OverRoom2 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                }
-                BurrowPosition::HallwayRight | BurrowPosition::OverRoom3 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::SideRoomRight => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                        && self.is_position_unoccupied(BurrowPosition::SideRoomRight)
-                }
-            },
-            BurrowPosition::HallwayMiddle => match to {
-                BurrowPosition::SideRoomLeft => {
-                    self.is_position_unoccupied(BurrowPosition::SideRoomLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                }
-                BurrowPosition::OverRoom0 | BurrowPosition::HallwayLeft => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                }
-                BurrowPosition::OverRoom1 => true,
-                BurrowPosition::HallwayMiddle => panic!("Checking move to the same spot"),
-                BurrowPosition::OverRoom2 => true,
-                BurrowPosition::HallwayRight | BurrowPosition::OverRoom3 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::SideRoomRight => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                        && self.is_position_unoccupied(BurrowPosition::SideRoomRight)
-                }
-            },
-            BurrowPosition::OverRoom2 => match to {
-                BurrowPosition::SideRoomLeft => {
-                    self.is_position_unoccupied(BurrowPosition::SideRoomLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                }
-                BurrowPosition::OverRoom0 | BurrowPosition::HallwayLeft => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                }
-                BurrowPosition::OverRoom1 | BurrowPosition::HallwayMiddle => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                }
-                BurrowPosition::OverRoom2 => panic!("Checking move to the same spot"),
-                BurrowPosition::HallwayRight | BurrowPosition::OverRoom3 => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::SideRoomRight => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                        && self.is_position_unoccupied(BurrowPosition::SideRoomRight)
-                }
-            },
-            BurrowPosition::HallwayRight => match to {
-                BurrowPosition::SideRoomLeft => {
-                    self.is_position_unoccupied(BurrowPosition::SideRoomLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                }
-                BurrowPosition::OverRoom0 | BurrowPosition::HallwayLeft => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                }
-                BurrowPosition::OverRoom1 | BurrowPosition::HallwayMiddle => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                }
-                BurrowPosition::OverRoom2 => true,
-                BurrowPosition::HallwayRight => panic!("Checking move to the same spot"),
-                BurrowPosition::OverRoom3 => true,
-                BurrowPosition::SideRoomRight => {
-                    self.is_position_unoccupied(BurrowPosition::SideRoomRight)
-                }
-            },
-            BurrowPosition::OverRoom3 => match to {
-                BurrowPosition::SideRoomLeft => {
-                    self.is_position_unoccupied(BurrowPosition::SideRoomLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::OverRoom0 | BurrowPosition::HallwayLeft => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::OverRoom1 | BurrowPosition::HallwayMiddle => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::OverRoom2 | BurrowPosition::HallwayRight => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::OverRoom3 => panic!("Checking move to the same spot"),
-                BurrowPosition::SideRoomRight => {
-                    self.is_position_unoccupied(BurrowPosition::SideRoomRight)
-                }
-            },
-            BurrowPosition::SideRoomRight => match to {
-                BurrowPosition::SideRoomLeft => {
-                    self.is_position_unoccupied(BurrowPosition::SideRoomLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::OverRoom0 | BurrowPosition::HallwayLeft => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayLeft)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::OverRoom1 | BurrowPosition::HallwayMiddle => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayMiddle)
-                        && self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::OverRoom2 | BurrowPosition::HallwayRight => {
-                    self.is_position_unoccupied(BurrowPosition::HallwayRight)
-                }
-                BurrowPosition::OverRoom3 => true,
-                BurrowPosition::SideRoomRight => panic!("Checking move to the same spot"),
-            },
+    fn moves(&self, state: &State) -> Vec<(State, Move)> {
+        let occupied = (0..state.len())
+            .filter(|&c| state[c] != 0)
+            .fold(0u128, |acc, c| acc | 1 << c);
+        let mut moves = Vec::new();
+        for (from, &pod) in state.iter().enumerate().filter(|(_, p)| **p != 0) {
+            let amphipod = pod - 1;
+            let home = &self.rooms[amphipod as usize];
+            let mut targets = Vec::new();
+            if let Place::Room(room, depth) = self.places[from] {
+                let settled = self.rooms[room][depth..].iter().all(|&c| state[c] == pod);
+                if room == amphipod as usize && settled {
+                    continue;
+                }
+                targets.extend(self.stops.iter().copied());
+            }
+            // only into its own room, once strangers have left, as deep as it goes
+            if home.iter().all(|&c| state[c] == 0 || state[c] == pod)
+                && let Some(deepest) = home.iter().rposition(|&c| state[c] == 0)
+            {
+                targets.push(home[deepest]);
+            }
+
+            for to in targets {
+                let (steps, walked) = self.paths[from][to];
+                if walked & occupied != 0 {
+                    continue;
+                }
+                let mut next = state.clone();
+                next[to] = pod;
+                next[from] = 0;
+                let mv = Move {
+                    amphipod,
+                    from: self.places[from],
+                    to: self.places[to],
+                    energy: steps * self.costs[amphipod as usize],
+                };
+                moves.push((next, mv));
+            }
         }
+        moves
     }
-}
 
-fn queue_move_into_room(
-    state: Burrow,
-    pod: Amphipod,
-    from: BurrowPosition,
-    current_cost: usize,
-    known_states: &mut PriorityQueue<Burrow, Reverse<usize>>,
-) {
-    let mut state = state;
-    let desired_room_pos = pod.desired_room_pos();
-    let can_move_over = state.can_move(from, desired_room_pos);
-    let desired_room = state.get_room_under_pos(desired_room_pos);
-    let can_move_into = desired_room.iter().all(|o| *o == pod);
-    if can_move_over && can_move_into {
-        let cost = {
-            let tiles_moved_into_room = state.room_depth - desired_room.len();
-            let tiles_moved = from.base_moves_to(desired_room_pos) + tiles_moved_into_room;
-            current_cost + (tiles_moved * pod.move_cost())
-        };
-        state.get_room_under_pos_mut(desired_room_pos).push(pod);
-        known_states.push_increase(state, Reverse(cost));
+    // Dijkstra over the burrow's states, remembering how each was reached
+    pub fn solve(&self) -> Option<Solution> {
+        let mut queue = PriorityQueue::new();
+        let mut best = HashMap::from([(self.start.clone(), 0)]);
+        let mut came_from: HashMap<State, (State, Move)> = HashMap::new();
+        queue.push(self.start.clone(), Reverse(0));
+
+        while let Some((state, Reverse(energy))) = queue.pop() {
+            if self.is_solved(&state) {
+                let mut moves = Vec::new();
+                let mut states = vec![state.clone()];
+                let mut current = &state;
+                while let Some((previous, mv)) = came_from.get(current) {
+                    moves.push(*mv);
+                    states.push(previous.clone());
+                    current = previous;
+                }
+                moves.reverse();
+                states.reverse();
+                return Some(Solution {
+                    energy,
+                    moves,
+                    states,
+                });
+            }
+            for (next, mv) in self.moves(&state) {
+                let total = energy + mv.energy;
+                if best.get(&next).is_some_and(|&b| b <= total) {
+                    continue;
+                }
+                best.insert(next.clone(), total);
+                came_from.insert(next.clone(), (state.clone(), mv));
+                queue.push_increase(next, Reverse(total));
+            }
+        }
+        None
     }
 }
 
-fn queue_moves_from_room(
-    state: Burrow,
-    current_cost: usize,
-    room_idx: usize,
-    known_states: &mut PriorityQueue<Burrow, Reverse<usize>>,
-) {
-    let mut state = state;
-    let pod = state.rooms[room_idx].pop().unwrap();
-    let start_pos = Burrow::pos_over_room_idx(room_idx);
-    let leave_room_moves = state.room_depth - state.rooms[room_idx].len();
-
-    for end_pos in &[
-        BurrowPosition::HallwayLeft,
-        BurrowPosition::HallwayMiddle,
-        BurrowPosition::HallwayRight,
-    ] {
-        if !state.can_move(start_pos, *end_pos) {
-            continue;
-        }
-        let cost = {
-            let tiles_moved = start_pos.base_moves_to(*end_pos) + leave_room_moves;
-            current_cost + (tiles_moved * pod.move_cost())
-        };
-        let mut new_state = state.clone();
-        match end_pos {
-            BurrowPosition::HallwayLeft => new_state.hallway_left = Some(pod),
-            BurrowPosition::HallwayMiddle => new_state.hallway_middle = Some(pod),
-            BurrowPosition::HallwayRight => new_state.hallway_right = Some(pod),
-            _ => panic!("Invalid end pos in queue moves from room"),
+const USAGE: &str = "\
+usage: aoc burrow [map] [options]
+
+Replays the cheapest way to sort the amphipods of a burrow diagram,
+this year's day 23 input unless a map file is given.
+
+options:
+  --part2           unfold the burrow first, like part 2 does
+  --animate         redraw the burrow in place instead of listing every step
+  --delay <ms>      time between frames when animating (default 500)";
+
+// Entry point of the `aoc burrow` subcommand
+pub fn run_replay(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let (mut part2, mut animate, mut delay) = (false, false, 500);
+    let mut args = args.iter().map(String::as_str);
+    while let Some(arg) = args.next() {
+        match arg {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "--part2" => part2 = true,
+            "--animate" => animate = true,
+            "--delay" => {
+                let ms = args.next().context("--delay needs milliseconds")?;
+                delay = ms.parse().context("Invalid delay")?;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => bail!("Unexpected argument {:?}\n\n{}", arg, USAGE),
         }
-        known_states.push_increase(new_state, Reverse(cost));
     }
-
-    if state.left_side_room[0].is_none() && state.can_move(start_pos, BurrowPosition::SideRoomLeft)
-    {
-        // shallow
-        {
-            let cost = {
-                let tiles_moved =
-                    start_pos.base_moves_to(BurrowPosition::SideRoomLeft) + leave_room_moves;
-                current_cost + (tiles_moved * pod.move_cost())
-            };
-            let mut new_state = state.clone();
-            new_state.left_side_room[0] = Some(pod);
-            known_states.push_increase(new_state, Reverse(cost));
+    let file = match path {
+        Some(path) => std::fs::read(path).with_context(|| format!("Failed reading {}", path))?,
+        None => include_bytes!("../../../../input/2021/23/input.txt").to_vec(),
+    };
+    let mut burrow = parse_burrow(&file)?;
+    if part2 {
+        burrow = burrow.unfolded(&UNFOLDED_ROWS)?;
+    }
+    let solution = burrow.solve().context("The amphipods can't be sorted")?;
+
+    let mut out = std::io::stdout().lock();
+    for frame in burrow.replay(&solution) {
+        match animate {
+            // clear the screen and go back to the top left
+            true => writeln!(out, "\x1b[2J\x1b[H{}", frame)?,
+            false => writeln!(out, "{}\n", frame)?,
         }
-        // deep
-        if state.left_side_room[1].is_none() {
-            let cost = {
-                let tiles_moved =
-                    start_pos.base_moves_to(BurrowPosition::SideRoomLeft) + leave_room_moves + 1;
-                current_cost + (tiles_moved * pod.move_cost())
-            };
-            let mut new_state = state.clone();
-            new_state.left_side_room[1] = Some(pod);
-            known_states.push_increase(new_state, Reverse(cost));
+        if animate {
+            out.flush()?;
+            std::thread::sleep(Duration::from_millis(delay));
         }
     }
+    writeln!(out, "total: {} energy", solution.energy)?;
+    Ok(())
+}
 
-    if state.right_side_room[0].is_none()
-        && state.can_move(start_pos, BurrowPosition::SideRoomRight)
-    {
-        // shallow
-        {
-            let cost = {
-                let tiles_moved =
-                    start_pos.base_moves_to(BurrowPosition::SideRoomRight) + leave_room_moves;
-                current_cost + (tiles_moved * pod.move_cost())
-            };
-            let mut new_state = state.clone();
-            new_state.right_side_room[0] = Some(pod);
-            known_states.push_increase(new_state, Reverse(cost));
-        }
-        // deep
-        if state.right_side_room[1].is_none() {
-            let cost = {
-                let tiles_moved =
-                    start_pos.base_moves_to(BurrowPosition::SideRoomRight) + leave_room_moves + 1;
-                current_cost + (tiles_moved * pod.move_cost())
-            };
-            let mut new_state = state.clone();
-            new_state.right_side_room[1] = Some(pod);
-            known_states.push_increase(new_state, Reverse(cost));
+fn parse_burrow(file: &[u8]) -> Result<Burrow> {
+    let rows = whole_input(lines(is_not("\r\n")))(file).diagnose(file)?;
+    Burrow::new(rows.into_iter().map(<[u8]>::to_vec).collect())
+}
+
+pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
+    let burrow = parse_burrow(file)?;
+    burrow
+        .unfolded(&UNFOLDED_ROWS)
+        .context("The burrow doesn't unfold for part 2")?;
+    Ok(burrow)
+}
+
+pub fn solve_part1(input: &SolverInput) -> usize {
+    input.solve().map_or(0, |s| s.energy)
+}
+
+pub fn solve_part2(input: &SolverInput) -> usize {
+    let burrow = input
+        .unfolded(&UNFOLDED_ROWS)
+        .expect("The burrow was unfolded when parsing");
+    burrow.solve().map_or(0, |s| s.energy)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &[u8] = concat!(
+        "#############\n",
+        "#...........#\n",
+        "###B#C#B#D###\n",
+        "  #A#D#C#A#\n",
+        "  #########\n",
+    )
+    .as_bytes();
+
+    rules::make_test_for_day!(example, EXAMPLE, 12521, 44169);
+
+    // replays the moves from the start, checking each one is legal
+    fn replay(burrow: &Burrow, solution: &Solution) -> State {
+        let mut state = burrow.start.clone();
+        for mv in solution.moves.iter() {
+            let (next, _) = burrow
+                .moves(&state)
+                .into_iter()
+                .find(|(_, m)| m == mv)
+                .expect("Illegal move");
+            state = next;
         }
+        state
     }
-}
 
-fn queue_possible_moves(
-    state: Burrow,
-    current_cost: usize,
-    known_states: &mut PriorityQueue<Burrow, Reverse<usize>>,
-) {
-    // from left side room
-    if let Some(pod) = state.left_side_room[0] {
-        // leaving shallow left side room
-        let mut new_state = state.clone();
-        new_state.left_side_room[0].take();
-        queue_move_into_room(
-            new_state,
-            pod,
-            BurrowPosition::SideRoomLeft,
-            current_cost,
-            known_states,
-        );
-    } else if let Some(pod) = state.left_side_room[1] {
-        // leaving deep left side room
-        let mut new_state = state.clone();
-        new_state.left_side_room[1].take();
-        queue_move_into_room(
-            new_state,
-            pod,
-            BurrowPosition::SideRoomLeft,
-            current_cost + pod.move_cost(),
-            known_states,
+    #[test]
+    fn other_layouts() {
+        let small = concat!("#######\n", "#.....#\n", "##B#A##\n", " #####\n");
+        let burrow = parse_burrow(small.as_bytes()).unwrap();
+        assert!(burrow.unfolded(&UNFOLDED_ROWS).is_err());
+        assert!(parse_input(small.as_bytes()).is_err());
+        assert_eq!(burrow.solve().unwrap().energy, 46);
+
+        // three deep rooms, a long hallway and flat costs
+        let wide = concat!(
+            "###############\n",
+            "#.............#\n",
+            "####C#A#B######\n",
+            "   #B#C#A#\n",
+            "   #A#B#C#\n",
+            "   #######\n",
         );
+        let burrow = parse_burrow(wide.as_bytes())
+            .unwrap()
+            .with_costs(vec![1, 1, 1])
+            .unwrap();
+        let solution = burrow.solve().unwrap();
+        let spent: usize = solution.moves.iter().map(|m| m.energy).sum();
+        assert_eq!(spent, solution.energy);
+        assert!(burrow.is_solved(&replay(&burrow, &solution)));
     }
-    // from hallway
-    if let Some(pod) = state.hallway_left {
-        let mut new_state = state.clone();
-        new_state.hallway_left.take();
-        queue_move_into_room(
-            new_state,
-            pod,
-            BurrowPosition::HallwayLeft,
-            current_cost,
-            known_states,
+
+    #[test]
+    fn move_sequence() {
+        let burrow = parse_input(EXAMPLE).unwrap();
+        let solution = burrow.solve().unwrap();
+        assert!(burrow.is_solved(&replay(&burrow, &solution)));
+        assert_eq!(
+            solution.to_string().lines().last(),
+            Some("total: 12521 energy")
         );
-    }
-    if let Some(pod) = state.hallway_middle {
-        let mut new_state = state.clone();
-        new_state.hallway_middle.take();
-        queue_move_into_room(
-            new_state,
-            pod,
-            BurrowPosition::HallwayMiddle,
-            current_cost,
-            known_states,
+        let mv = Move {
+            amphipod: 1,
+            from: Place::Room(2, 0),
+            to: Place::Hallway(3),
+            energy: 40,
+        };
+        assert_eq!(
+            mv.to_string(),
+            "B from room C spot 1 to hallway 3 (40 energy)"
         );
     }
-    if let Some(pod) = state.hallway_right {
-        let mut new_state = state.clone();
-        new_state.hallway_right.take();
-        queue_move_into_room(
-            new_state,
-            pod,
-            BurrowPosition::HallwayRight,
-            current_cost,
-            known_states,
+
+    #[test]
+    fn replay_frames() {
+        let burrow = parse_input(EXAMPLE).unwrap();
+        let solution = burrow.solve().unwrap();
+        let frames = burrow.replay(&solution);
+        assert_eq!(frames.len(), solution.moves.len() + 1);
+        assert_eq!(
+            frames[0],
+            format!("start\n{}", String::from_utf8_lossy(EXAMPLE).trim_end())
         );
+        let last = frames.last().unwrap();
+        assert!(last.lines().next().unwrap().ends_with(", 12521 total"));
+        assert!(last.contains("###A#B#C#D###\n  #A#B#C#D#"));
     }
-    // from right side room
-    if let Some(pod) = state.right_side_room[0] {
-        // leaving shallow right side room
-        let mut new_state = state.clone();
-        new_state.right_side_room[0].take();
-        queue_move_into_room(
-            new_state,
-            pod,
-            BurrowPosition::SideRoomRight,
-            current_cost,
-            known_states,
+
+    #[test]
+    fn bad_burrows() {
+        let error = |map: &str| parse_input(map.as_bytes()).unwrap_err().to_string();
+        assert_eq!(
+            error("#####\n#...#\n##E##\n"),
+            "There's no room for amphipod E"
         );
-    } else if let Some(pod) = state.right_side_room[1] {
-        // leaving deep right side room
-        let mut new_state = state.clone();
-        new_state.right_side_room[1].take();
-        queue_move_into_room(
-            new_state,
-            pod,
-            BurrowPosition::SideRoomRight,
-            current_cost + pod.move_cost(),
-            known_states,
+        assert_eq!(
+            error("#######\n#.....#\n##A#A##\n"),
+            "There are 2 amphipods A for a room of 1"
         );
     }
-
-    for room_idx in 0..ROOM_COUNT {
-        if state.room_needs_popping(room_idx) {
-            queue_moves_from_room(state.clone(), current_cost, room_idx, known_states);
-        }
-    }
-}
-
-fn calc_solve_cost(burrow: Burrow) -> usize {
-    let mut states = PriorityQueue::new();
-    states.push(burrow, Reverse(0));
-    while let Some((state, cost)) = states.pop() {
-        if state.is_solved() {
-            return cost.0;
-        }
-        queue_possible_moves(state, cost.0, &mut states);
-    }
-
-    0
-}
-
-pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
-    let amphipods = {
-        let prefix = tag(b"#############\n#...........#\n###");
-        let line_sep = tag(b"###\n  #");
-        let room_sep = tag(b"#");
-        let abcd_alt = alt((tag(b"A"), tag(b"B"), tag(b"C"), tag(b"D")));
-        let abcd = map_opt(abcd_alt, Amphipod::from_ascii);
-        let line_rooms = separated_list1(room_sep, abcd);
-        let rooms = separated_list1::<_, _, _, nom::error::Error<_>, _, _>(line_sep, line_rooms);
-        preceded(prefix, rooms)(file)
-    }
-    .map_err(|_| anyhow!("Failed parsing amphipods"))?
-    .1
-    .into_iter()
-    .flatten();
-
-    let rooms = {
-        let mut rooms = [vec![], vec![], vec![], vec![]];
-        let mut room_it = 0;
-        for pod in amphipods.into_iter().rev() {
-            rooms[ROOM_COUNT - room_it - 1].push(pod);
-            room_it = (room_it + 1) % ROOM_COUNT;
-        }
-        rooms
-    };
-
-    Ok(Burrow {
-        rooms,
-        room_depth: ROOM_DEPTH,
-        hallway_left: None,
-        hallway_middle: None,
-        hallway_right: None,
-        left_side_room: [None; SIDE_ROOM_DEPTH],
-        right_side_room: [None; SIDE_ROOM_DEPTH],
-    })
-}
-
-pub fn solve_part1(input: &SolverInput) -> usize {
-    calc_solve_cost(input.clone())
-}
-
-pub fn solve_part2(input: &SolverInput) -> usize {
-    let mut state = input.clone();
-    state.room_depth += 2;
-    state.rooms[0].insert(1, Amphipod::Desert);
-    state.rooms[0].insert(1, Amphipod::Desert);
-    state.rooms[1].insert(1, Amphipod::Copper);
-    state.rooms[1].insert(1, Amphipod::Bronze);
-    state.rooms[2].insert(1, Amphipod::Bronze);
-    state.rooms[2].insert(1, Amphipod::Amber);
-    state.rooms[3].insert(1, Amphipod::Amber);
-    state.rooms[3].insert(1, Amphipod::Copper);
-
-    calc_solve_cost(state)
 }
@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 use anyhow::{Context, Result, anyhow};
-use ndarray::{Array2, Axis, Dim, iter::AxisIterMut, parallel::prelude::*};
+use ndarray::Array2;
 use nom::{
     IResult,
     branch::alt,
@@ -9,9 +9,14 @@ use nom::{
     multi::{many1, separated_list1},
 };
 
+use crate::automaton::{
+    Automaton, Offset, Phase, Rule, Settled,
+    dense::{Dense, Edges},
+};
+
 type SolverInput = Array2<Cell>;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Cell {
     Empty,
     East,
@@ -32,45 +37,16 @@ impl Display for Cell {
     }
 }
 
-fn step(state: &mut Array2<Cell>) -> bool {
-    fn par_step_axis(axis: AxisIterMut<Cell, Dim<[usize; 1]>>, cell: Cell) -> bool {
-        axis.into_par_iter()
-            .fold(
-                || false,
-                |oldret, mut line| {
-                    let mut ret = false;
-                    let len = line.shape()[0];
-
-                    let can_roll_around = line[0] == Cell::Empty && line[len - 1] == cell;
-
-                    let mut it = 1;
-                    while it < len {
-                        if line[it] != Cell::Empty {
-                            it += 1;
-                            continue;
-                        }
-                        if line[it - 1] == cell {
-                            line[it] = cell;
-                            line[it - 1] = Cell::Empty;
-                            ret = true;
-                            it += 1;
-                        }
-                        it += 1;
-                    }
-
-                    if can_roll_around {
-                        line[it - 1] = Cell::Empty;
-                        line[0] = cell;
-                    }
-
-                    oldret || ret
-                },
-            )
-            .reduce(|| false, |b, ob| b || ob)
-    }
-
-    par_step_axis(state.axis_iter_mut(Axis(0)), Cell::East)
-        | par_step_axis(state.axis_iter_mut(Axis(1)), Cell::South)
+// A herd moves into the empty cell ahead of it, all at once
+fn herd_moves(herd: Cell, behind: Offset) -> Phase<Cell> {
+    let ahead = (-behind.0, -behind.1);
+    Phase::Once(Rule::new(&[behind, ahead], move |cell, around| {
+        match (cell, around[0], around[1]) {
+            (Cell::Empty, b, _) if b == herd => herd,
+            (c, _, Cell::Empty) if c == herd => Cell::Empty,
+            (c, _, _) => c,
+        }
+    }))
 }
 
 pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
@@ -97,12 +73,15 @@ pub fn parse_input(file: &[u8]) -> Result<SolverInput> {
 }
 
 pub fn solve_part1(input: &SolverInput) -> u32 {
-    let mut state = input.clone();
-    let mut steps = 1;
-    while step(&mut state) {
-        steps += 1;
+    let space = Dense::new(input.clone(), Edges::Wrap);
+    let phases = vec![
+        herd_moves(Cell::East, (0, -1)),
+        herd_moves(Cell::South, (-1, 0)),
+    ];
+    match Automaton::new(space, phases).run_until_settled(usize::MAX) {
+        Ok(Some(Settled::Steady { step })) => step as u32,
+        _ => 0,
     }
-    steps
 }
 
 pub fn solve_part2(_: &SolverInput) -> i64 {
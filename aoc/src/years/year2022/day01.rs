@@ -1,21 +1,17 @@
 use std::{collections::BinaryHeap, iter::FromIterator};
 
-use anyhow::{Result, anyhow};
-use nom::{bytes::complete::tag, multi::separated_list1};
+use anyhow::Result;
 
-use crate::parse::parse_unsigned;
+use crate::parse::{Diagnose, lines, paragraphs, parse_unsigned, whole_input};
 
 type ParserOutput = Vec<u32>;
 type SolverInput = [u32];
 
 pub fn parse_input(file: &[u8]) -> Result<ParserOutput> {
     let parse_elf = |entries| {
-        separated_list1(tag(b"\n"), parse_unsigned)(entries)
-            .map(|(rest, calories)| (rest, calories.iter().sum()))
+        lines(parse_unsigned)(entries).map(|(rest, calories)| (rest, calories.iter().sum()))
     };
-    separated_list1(tag("\n\n"), parse_elf)(file)
-        .map_err(|_| anyhow!("Parser failed"))
-        .map(|t| t.1)
+    whole_input(paragraphs(parse_elf))(file).diagnose(file)
 }
 
 pub fn solve_part1(input: &SolverInput) -> u32 {
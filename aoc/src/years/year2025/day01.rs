@@ -1,10 +1,7 @@
-use anyhow::{Result, anyhow};
-use nom::{
-    bytes::complete::tag, character::complete::anychar, combinator::map_opt,
-    multi::separated_list1, sequence::pair,
-};
+use anyhow::Result;
+use nom::{character::complete::anychar, combinator::map_opt, sequence::pair};
 
-use crate::parse::parse_unsigned;
+use crate::parse::{Diagnose, lines, parse_unsigned, whole_input};
 
 type Rotation = (bool, i32);
 type ParserOutput = Vec<Rotation>;
@@ -16,9 +13,7 @@ pub fn parse_input(file: &[u8]) -> Result<ParserOutput> {
         'R' => Some(true),
         _ => None,
     };
-    separated_list1(tag("\n"), pair(map_opt(anychar, map_lr), parse_unsigned))(file)
-        .map_err(|_| anyhow!("Parser failed"))
-        .map(|t| t.1)
+    whole_input(lines(pair(map_opt(anychar, map_lr), parse_unsigned)))(file).diagnose(file)
 }
 
 pub fn solve_part1(input: &SolverInput) -> u32 {
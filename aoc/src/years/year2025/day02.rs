@@ -3,13 +3,13 @@ use std::{
     iter::repeat,
 };
 
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use nom::{
-    Err, bytes::complete::tag, character::complete::digit1, combinator::map_opt, error::Error,
-    multi::separated_list1, sequence::separated_pair,
+    bytes::complete::tag, character::complete::digit1, combinator::map_opt, multi::separated_list1,
+    sequence::separated_pair,
 };
 
-use crate::parse::parse_unsigned_radix;
+use crate::parse::{Diagnose, parse_unsigned_radix, whole_input};
 
 type Range<'a> = (&'a [u8], &'a [u8], u64, u64);
 type ParserOutput<'a> = Vec<Range<'a>>;
@@ -24,12 +24,11 @@ pub fn parse_input(file: &[u8]) -> Result<ParserOutput<'_>> {
             parse_unsigned_radix(r, 10)?,
         ))
     };
-    separated_list1(
+    whole_input(separated_list1(
         tag(b","),
         map_opt(separated_pair(digit1, tag(b"-"), digit1), map_range),
-    )(file)
-    .map_err(|_: Err<Error<_>>| anyhow!("Parser failed"))
-    .map(|t| t.1)
+    ))(file)
+    .diagnose(file)
 }
 
 pub fn solve_part1(input: &SolverInput) -> u64 {
@@ -1,15 +1,13 @@
-use anyhow::{Result, anyhow};
-use nom::{
-    bytes::complete::tag, character::complete::digit1, error::Error, multi::separated_list1,
-};
+use anyhow::Result;
+use nom::character::complete::digit1;
+
+use crate::parse::{Diagnose, lines, whole_input};
 
 type ParserOutput<'a> = Vec<&'a [u8]>;
 type SolverInput<'a> = [&'a [u8]];
 
 pub fn parse_input(file: &[u8]) -> Result<ParserOutput<'_>> {
-    separated_list1(tag("\n"), digit1)(file)
-        .map_err(|_: nom::Err<Error<_>>| anyhow!("Parser failed"))
-        .map(|t| t.1)
+    whole_input(lines(digit1))(file).diagnose(file)
 }
 
 pub fn solve_part1(input: &SolverInput) -> u32 {
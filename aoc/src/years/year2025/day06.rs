@@ -1,15 +1,15 @@
 use anyhow::{Result, anyhow};
 use nom::{
     Err,
-    bytes::complete::{is_a, is_not, tag},
-    character::complete::space1,
+    bytes::complete::{is_a, is_not},
+    character::complete::{line_ending, space1},
     combinator::recognize,
     error::Error,
-    multi::{many1, separated_list1},
+    multi::many1,
     sequence::{pair, separated_pair},
 };
 
-use crate::parse::parse_unsigned_radix;
+use crate::parse::{fixed_width, lines, parse_unsigned_radix};
 
 type ParserOutput<'a> = Vec<(u8, Vec<&'a [u8]>)>;
 type SolverInput<'a> = [(u8, Vec<&'a [u8]>)];
@@ -19,26 +19,27 @@ const MUL_SIGN: u8 = b'*';
 
 pub fn parse_input(file: &[u8]) -> Result<ParserOutput<'_>> {
     let (rows_raw, ops) = separated_pair(
-        separated_list1(tag(b"\n"), is_not([ADD_SIGN, MUL_SIGN, b'\n'])),
-        tag(b"\n"),
+        lines(is_not([ADD_SIGN, MUL_SIGN, b'\r', b'\n'])),
+        line_ending,
         many1(recognize(pair(is_a([ADD_SIGN, MUL_SIGN]), space1))),
     )(file)
     .map_err(|_: Err<Error<_>>| anyhow!("Failed parsing cells"))?
     .1;
 
-    let mut xoff = 0;
+    let widths: Vec<_> = ops.iter().map(|op| op.len()).collect();
+    let rows = rows_raw
+        .into_iter()
+        .map(|row| fixed_width::<Error<_>>(&widths)(row).map(|t| t.1))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| anyhow!("Failed splitting rows into columns"))?;
+
     let mut out = Vec::with_capacity(ops.len());
-    for op in ops.into_iter() {
-        let len = op.len();
-        let mut nums = Vec::with_capacity(rows_raw.len());
-        for row in &rows_raw {
-            nums.push(&row[xoff..(xoff + len)]);
-        }
+    for (i, op) in ops.into_iter().enumerate() {
+        let nums = rows.iter().map(|row| row[i]).collect();
         let sign = *op
             .first()
             .expect("At least one element guaranteed by parsers");
         out.push((sign, nums));
-        xoff += len;
     }
 
     Ok(out)
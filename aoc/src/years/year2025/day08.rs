@@ -3,13 +3,14 @@ use std::{cmp::Reverse, collections::BTreeSet};
 use anyhow::{Result, anyhow};
 use nom::{
     bytes::complete::tag,
+    combinator::map,
     multi::separated_list1,
     sequence::{terminated, tuple},
 };
 
-use crate::parse::parse_unsigned;
+use crate::{geometry::Vec3, parse::parse_unsigned};
 
-type Coords = (u64, u64, u64);
+type Coords = Vec3<u64>;
 type ParserOutput = Vec<Coords>;
 type SolverInput = [Coords];
 
@@ -18,11 +19,14 @@ struct DistIndicies(u64, (usize, usize));
 pub fn parse_input(file: &[u8]) -> Result<ParserOutput> {
     separated_list1(
         tag(b"\n"),
-        tuple((
-            terminated(parse_unsigned, tag(b",")),
-            terminated(parse_unsigned, tag(b",")),
-            parse_unsigned,
-        )),
+        map(
+            tuple((
+                terminated(parse_unsigned, tag(b",")),
+                terminated(parse_unsigned, tag(b",")),
+                parse_unsigned,
+            )),
+            |(x, y, z)| Vec3::new(x, y, z),
+        ),
     )(file)
     .map_err(|_| anyhow!("Failed parsing cells"))
     .map(|t| t.1)
@@ -36,16 +40,13 @@ pub fn solve_part1(input: &SolverInput) -> usize {
 pub fn solve_part2(input: &SolverInput) -> u64 {
     let one_group = |_, groups: &[BTreeSet<_>]| groups.len() == 1 && groups[0].len() == input.len();
     let (last_a, last_b) = solve_gen(input, one_group).1;
-    input[last_a].0 * input[last_b].0
+    input[last_a].x * input[last_b].x
 }
 
 fn solve_gen<F>(input: &SolverInput, term_cond: F) -> (usize, (usize, usize))
 where
     F: Fn(usize, &[BTreeSet<usize>]) -> bool,
 {
-    let distance2 = |l: Coords, r: Coords| {
-        l.0.abs_diff(r.0).pow(2) + l.1.abs_diff(r.1).pow(2) + l.2.abs_diff(r.2).pow(2)
-    };
     let find_which_group = |groups: &[BTreeSet<_>], elem| {
         groups
             .iter()
@@ -57,7 +58,7 @@ where
         let elems = input.len();
         let dist_coord_it = (0..elems).flat_map(|from| {
             ((from + 1)..elems)
-                .map(move |to| DistIndicies(distance2(input[from], input[to]), (from, to)))
+                .map(move |to| DistIndicies(input[from].distance2_to(input[to]), (from, to)))
         });
         BTreeSet::from_iter(dist_coord_it)
     };
@@ -129,6 +130,7 @@ mod test {
     use concat_with::concat_line;
 
     use super::{SolverInput, parse_input, solve_gen, solve_part2};
+    use crate::geometry::Vec3;
 
     const EXAMPLE: &[u8] = concat_line!(
         "162,817,812",
@@ -160,26 +162,26 @@ mod test {
         assert_eq!(
             parsed,
             [
-                (162, 817, 812),
-                (57, 618, 57),
-                (906, 360, 560),
-                (592, 479, 940),
-                (352, 342, 300),
-                (466, 668, 158),
-                (542, 29, 236),
-                (431, 825, 988),
-                (739, 650, 466),
-                (52, 470, 668),
-                (216, 146, 977),
-                (819, 987, 18),
-                (117, 168, 530),
-                (805, 96, 715),
-                (346, 949, 466),
-                (970, 615, 88),
-                (941, 993, 340),
-                (862, 61, 35),
-                (984, 92, 344),
-                (425, 690, 689),
+                Vec3::new(162, 817, 812),
+                Vec3::new(57, 618, 57),
+                Vec3::new(906, 360, 560),
+                Vec3::new(592, 479, 940),
+                Vec3::new(352, 342, 300),
+                Vec3::new(466, 668, 158),
+                Vec3::new(542, 29, 236),
+                Vec3::new(431, 825, 988),
+                Vec3::new(739, 650, 466),
+                Vec3::new(52, 470, 668),
+                Vec3::new(216, 146, 977),
+                Vec3::new(819, 987, 18),
+                Vec3::new(117, 168, 530),
+                Vec3::new(805, 96, 715),
+                Vec3::new(346, 949, 466),
+                Vec3::new(970, 615, 88),
+                Vec3::new(941, 993, 340),
+                Vec3::new(862, 61, 35),
+                Vec3::new(984, 92, 344),
+                Vec3::new(425, 690, 689),
             ]
         );
     }
@@ -1,21 +1,23 @@
-
 use anyhow::{Result, anyhow};
 use nom::{
-    bytes::complete::tag,
-    multi::separated_list1,
-    sequence::separated_pair,
+    bytes::complete::tag, combinator::map, multi::separated_list1, sequence::separated_pair,
 };
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
-use crate::{parse::parse_unsigned, traits::Intersect};
+use crate::{
+    geometry::{polygon::Polygon, segment::Point},
+    parse::parse_unsigned,
+};
 
-type ParserOutput = Vec<(u64, u64)>;
-type SolverInput = [(u64, u64)];
+type ParserOutput = Vec<Point>;
+type SolverInput = [Point];
 
 pub fn parse_input(file: &[u8]) -> Result<ParserOutput> {
     separated_list1(
         tag(b"\n"),
-        separated_pair(parse_unsigned, tag(b","), parse_unsigned),
+        map(
+            separated_pair(parse_unsigned, tag(b","), parse_unsigned),
+            |(x, y)| Point::new(x, y),
+        ),
     )(file)
     .map_err(|_| anyhow!("Failed parsing cells"))
     .map(|t| t.1)
@@ -31,42 +33,19 @@ pub fn solve_part1(input: &SolverInput) -> u64 {
 
 pub fn solve_part2(input: &SolverInput) -> u64 {
     let len = input.len();
-    let rects: Vec<_> = (0..len)
-        .flat_map(|i| ((i + 1)..len).map(move |j| (rectsize(input[i], input[j]), i, j)))
-        .collect();
-    // tuples (is same x, value of the same dimension, range of the diff dimension)
-    let connections: Vec<_> = [(len - 1, 0)]
-        .into_iter()
-        .chain((0..(len - 1)).map(|i| (i, i + 1)))
-        .map(|(i, j)| {
-            let (l, r) = (input[i], input[j]);
-            match l.1 == r.1 {
-                true => (false, l.1, l.0.min(r.0)..=l.0.max(r.0)),
-                false => (true, l.0, l.1.min(r.1)..=l.1.max(r.1)),
-            }
-        })
-        .collect();
-
-    rects
-        .par_iter()
-        .filter_map(|&(size, i, j)| {
-            let (l, r) = (input[i], input[j]);
-            let x_inner = (l.0.min(r.0) + 1)..l.0.max(r.0);
-            let y_inner = (l.1.min(r.1) + 1)..l.1.max(r.1);
-            connections
-                .iter()
-                .find(|&t| match t {
-                    (true, x, ys) => x_inner.contains(x) && ys.intersect_with(&y_inner).is_some(),
-                    (false, y, xs) => y_inner.contains(y) && xs.intersect_with(&x_inner).is_some(),
-                })
-                .map_or(Some(size), |_| None)
-        })
+    let index = Polygon::new(input.to_vec())
+        .containment_index()
+        .expect("Red tiles should form a rectilinear loop");
+    (0..len)
+        .flat_map(|i| ((i + 1)..len).map(move |j| (input[i], input[j])))
+        .filter(|&(l, r)| index.contains_rect(l, r))
+        .map(|(l, r)| rectsize(l, r))
         .max()
         .expect("Should have at least one valid rect")
 }
 
-fn rectsize(l: (u64, u64), r: (u64, u64)) -> u64 {
-    (l.0.abs_diff(r.0) + 1) * (l.1.abs_diff(r.1) + 1)
+fn rectsize(l: Point, r: Point) -> u64 {
+    (l.x.abs_diff(r.x) + 1) * (l.y.abs_diff(r.y) + 1)
 }
 
 #[cfg(test)]
@@ -84,14 +63,14 @@ mod test {
         assert_eq!(
             parsed,
             [
-                (7, 1),
-                (11, 1),
-                (11, 7),
-                (9, 7),
-                (9, 5),
-                (2, 5),
-                (2, 3),
-                (7, 3),
+                Point::new(7, 1),
+                Point::new(11, 1),
+                Point::new(11, 7),
+                Point::new(9, 7),
+                Point::new(9, 5),
+                Point::new(2, 5),
+                Point::new(2, 3),
+                Point::new(7, 3),
             ]
         );
     }